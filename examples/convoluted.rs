@@ -2,8 +2,13 @@
 
 use hecs::World;
 use rand::{rngs::StdRng, Rng, SeedableRng};
-use std::time::{Duration, Instant};
-use yaks::{Executor, QueryMarker, SystemContext};
+#[cfg(feature = "parallel")]
+use rayon::iter::ParallelIterator;
+use std::{
+    cmp::Ordering,
+    time::{Duration, Instant},
+};
+use yaks::{BatchSize, Executor, QueryMarker, SystemContext};
 
 // Each of the tests will be ran this many times.
 const ITERATIONS: u32 = 100;
@@ -27,10 +32,6 @@ impl SpawnedEntities {
     pub const fn batch_size_no_acceleration(&self) -> u32 {
         self.no_acceleration / Self::BATCH_TASKS
     }
-
-    pub const fn batch_size_with_acceleration(&self) -> u32 {
-        self.with_acceleration / Self::BATCH_TASKS
-    }
 }
 
 // Example components and/or resources.
@@ -59,16 +60,18 @@ fn motion(
     yaks::batch(
         &mut context.query(no_acceleration),
         spawned.batch_size_no_acceleration(),
-        |_entity, (mut pos, vel)| {
+        |_entity, (pos, vel)| {
             pos.0 += vel.0;
             pos.1 += vel.1;
         },
     );
     // If the default `parallel` feature is disabled this simply iterates in a single thread.
+    // Instead of a fixed size, `BatchSize::auto()` picks the size of batches for each
+    // archetype the query matches, based on the number of entities and threads.
     yaks::batch(
         &mut context.query(with_acceleration),
-        spawned.batch_size_with_acceleration(),
-        |_entity, (mut pos, mut vel, acc)| {
+        BatchSize::auto(context.archetypes()),
+        |_entity, (pos, vel, acc)| {
             vel.0 += acc.0;
             vel.1 += acc.1;
             pos.0 += vel.0;
@@ -80,13 +83,18 @@ fn motion(
 // A system that tracks the highest velocity among all entities.
 fn find_highest_velocity(
     context: SystemContext,
-    highest: &mut Velocity,
+    (highest, spawned): (&mut Velocity, &SpawnedEntities),
     query: QueryMarker<&Velocity>,
 ) {
-    // This cannot be batched as is because it needs mutable access to `highest`;
-    // however, it's possible to work around that by using channels and/or `RwLock`.
-    for (_entity, vel) in context.query(query).iter() {
-        if vel.0 * vel.0 + vel.1 * vel.1 > highest.0 * highest.0 + highest.1 * highest.1 {
+    // `yaks::par_iter()` turns a query into a `rayon::iter::ParallelIterator` over it's batches,
+    // allowing reductions without sharing `highest` between threads. If the default `parallel`
+    // feature is disabled it returns a plain `Iterator`; `max_by()` is available on both.
+    let speed = |vel: &Velocity| vel.0 * vel.0 + vel.1 * vel.1;
+    if let Some(vel) = yaks::par_iter(&mut context.query(query), spawned.batch_size_all())
+        .map(|(_entity, vel)| vel)
+        .max_by(|a, b| speed(a).partial_cmp(&speed(b)).unwrap_or(Ordering::Equal))
+    {
+        if speed(vel) > speed(highest) {
             highest.0 = vel.0;
             highest.1 = vel.1;
         }
@@ -101,11 +109,11 @@ fn color(
 ) {
     // Of course, it's possible to use resources mutably and still batch queries if
     // mutation happens outside batching.
-    let blue = rng.gen_range(0.0, 1.0);
+    let blue = rng.gen_range(0.0..1.0);
     yaks::batch(
         &mut context.query(query),
         spawned.batch_size_all(),
-        |_entity, (pos, vel, mut col)| {
+        |_entity, (pos, vel, col)| {
             col.0 = pos.0.abs() / 1000.0;
             col.1 = vel.1.abs() / 100.0;
             col.2 = blue;
@@ -154,8 +162,8 @@ fn main() {
     world.spawn_batch((0..(to_spawn / 2)).map(|_| {
         spawned.no_acceleration += 1;
         (
-            Position(rng.gen_range(-100.0, 100.0), rng.gen_range(-100.0, 100.0)),
-            Velocity(rng.gen_range(-1.0, 1.0), rng.gen_range(-1.0, 1.0)),
+            Position(rng.gen_range(-100.0..100.0), rng.gen_range(-100.0..100.0)),
+            Velocity(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)),
             Color(0.0, 0.0, 0.0, 1.0),
         )
     }));
@@ -163,9 +171,9 @@ fn main() {
     world.spawn_batch((0..(to_spawn / 2)).map(|_| {
         spawned.with_acceleration += 1;
         (
-            Position(rng.gen_range(-100.0, 100.0), rng.gen_range(-100.0, 100.0)),
-            Velocity(rng.gen_range(-10.0, 10.0), rng.gen_range(-10.0, 10.0)),
-            Acceleration(rng.gen_range(-1.0, 1.0), rng.gen_range(-1.0, 1.0)),
+            Position(rng.gen_range(-100.0..100.0), rng.gen_range(-100.0..100.0)),
+            Velocity(rng.gen_range(-10.0..10.0), rng.gen_range(-10.0..10.0)),
+            Acceleration(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)),
             Color(0.0, 0.0, 0.0, 1.0),
         )
     }));
@@ -237,7 +245,7 @@ fn main() {
     for _ in 0..ITERATIONS {
         let time = Instant::now();
         motion.run(world, &spawned);
-        find_highest_velocity.run(world, &mut highest_velocity);
+        find_highest_velocity.run(world, (&mut highest_velocity, &spawned));
         color.run(world, (&spawned, &mut rng));
         find_average_color.run(world, (&mut average_color, &spawned));
         elapsed += time.elapsed();
@@ -255,5 +263,5 @@ fn main() {
         },
     );
     find_average_color.run(world, (&mut average_color, &spawned));
-    assert!((average_color.3 - 0.5).abs() < std::f32::EPSILON);
+    assert!((average_color.3 - 0.5).abs() < f32::EPSILON);
 }
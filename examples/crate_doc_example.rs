@@ -1,7 +1,7 @@
 //! Copy of the crate level documentation & readme example.
 
 use hecs::{With, Without, World};
-use yaks::{Executor, QueryMarker};
+use yaks::{BatchSize, Executor, QueryMarker};
 
 fn main() {
     let mut world = World::new();
@@ -56,9 +56,10 @@ fn system_with_two_queries(
             *unsigned += average.round() as u32;
         },
     );
+    // Batch size can also be picked for each archetype the query matches.
     yaks::batch(
         &mut context.query(without_f32),
-        entities / 8,
+        BatchSize::auto(context.archetypes()),
         |_entity, unsigned| {
             *unsigned *= average.round() as u32;
         },
@@ -33,6 +33,43 @@ impl BorrowTypeSet {
     }
 }
 
+/// Identifies a field of a resource: index of the resource in executor's tuple,
+/// and type of the field.
+pub type FieldKey = (usize, TypeId);
+
+pub struct BorrowFieldSet {
+    pub immutable: HashSet<FieldKey>,
+    pub mutable: HashSet<FieldKey>,
+}
+
+impl BorrowFieldSet {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self {
+            immutable: HashSet::new(),
+            mutable: HashSet::new(),
+        }
+    }
+
+    /// Appends a bit for each of the fields to the resource set, placed after `resources`
+    /// bits of whole resources. Borrowing a whole resource borrows all of it's fields.
+    pub fn expand_into(self, set: &mut BorrowSet, resources: usize, all_fields: &[FieldKey]) {
+        set.immutable.grow(resources + all_fields.len());
+        set.mutable.grow(resources + all_fields.len());
+        all_fields
+            .iter()
+            .enumerate()
+            .for_each(|(index, element @ (resource, _))| {
+                if self.immutable.contains(element) || set.immutable.contains(*resource) {
+                    set.immutable.insert(resources + index);
+                }
+                if self.mutable.contains(element) || set.mutable.contains(*resource) {
+                    set.mutable.insert(resources + index);
+                }
+            });
+    }
+}
+
 pub struct BorrowSet {
     pub immutable: FixedBitSet,
     pub mutable: FixedBitSet,
@@ -51,6 +88,15 @@ impl BorrowSet {
             && self.mutable.is_disjoint(&other.immutable)
             && self.immutable.is_disjoint(&other.mutable)
     }
+
+    /// Same as `::is_compatible()`, except shared borrows of `exclusive` bits also conflict.
+    pub fn is_compatible_exclusive(&self, other: &BorrowSet, exclusive: &FixedBitSet) -> bool {
+        self.is_compatible(other)
+            && self
+                .immutable
+                .intersection(&other.immutable)
+                .all(|bit| !exclusive.contains(bit))
+    }
 }
 
 #[derive(Default)]
@@ -66,17 +112,20 @@ impl ArchetypeSet {
             && self.immutable.is_disjoint(&other.mutable)
     }
 
-    pub fn set_bits_for_query<Q>(&mut self, world: &World)
-    where
-        Q: Query,
-    {
+    pub fn reset(&mut self, world: &World) {
         self.immutable.clear();
         self.mutable.clear();
-        let iterator = world.archetypes();
-        let bits = iterator.len();
+        let bits = world.archetypes().len();
         self.immutable.grow(bits);
         self.mutable.grow(bits);
-        iterator
+    }
+
+    pub fn set_bits_for_query<Q>(&mut self, world: &World)
+    where
+        Q: Query,
+    {
+        world
+            .archetypes()
             .enumerate()
             .filter_map(|(index, archetype)| archetype.access::<Q>().map(|access| (index, access)))
             .for_each(|(archetype, access)| match access {
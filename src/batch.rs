@@ -1,9 +1,165 @@
-use hecs::{Entity, Fetch, Query, QueryBorrow};
+use hecs::{Archetype, Entity, Fetch, Query, QueryBorrow};
+use parking_lot::Mutex;
+use std::time::Duration;
+
+use hecs::BatchedIter;
+#[cfg(not(feature = "parallel"))]
+use hecs::QueryIter;
+#[cfg(feature = "parallel")]
+use rayon::iter::{
+    plumbing::UnindexedConsumer, IntoParallelIterator, ParallelIterator as RayonParallelIterator,
+};
+
+use crate::{resource::partial_index, QueryEntities};
+
+/// Targeted number of batches per thread when sizing them automatically,
+/// so that threads running out of work have some to steal.
+#[cfg(feature = "parallel")]
+const TASKS_PER_THREAD: u32 = 4;
+
+/// Smallest automatically sized batch, if cost of processing an item is not known.
+#[cfg(feature = "parallel")]
+const MIN_AUTO_BATCH: u32 = 64;
+
+/// Approximate overhead of scheduling a batch, which automatically sized batches
+/// are made large enough to amortize if cost of processing an item is known.
+#[cfg(feature = "parallel")]
+const BATCH_OVERHEAD: Duration = Duration::from_micros(5);
+
+/// Upper bound on how many pieces of the query automatic sizing splits each batch into.
+#[cfg(feature = "parallel")]
+const MAX_GRANULES_PER_BATCH: u32 = 64;
+
+/// Size of batches a query is split into by [`yaks::batch()`](fn.batch.html).
+///
+/// A `u32` converts into a fixed size. Automatic sizing splits each archetype matched by
+/// the query separately, based on the number of entities in it, the number of threads
+/// in the current `rayon` thread pool, and, optionally, the cost of processing a single
+/// item, as estimated by the caller; it's not measured.
+///
+/// # Example
+/// ```rust
+/// # use yaks::{BatchSize, Executor, QueryMarker};
+/// # use std::time::Duration;
+/// struct Velocity(f32);
+///
+/// let mut world = hecs::World::new();
+/// world.spawn_batch((0..1000).map(|index| (Velocity(index as f32),)));
+/// let mut executor = Executor::<()>::builder()
+///     .system(|context, _resources: (), query: QueryMarker<&mut Velocity>| {
+///         yaks::batch(
+///             &mut context.query(query),
+///             BatchSize::auto(context.archetypes()).with_item_cost(Duration::from_nanos(50)),
+///             |_entity, velocity| velocity.0 *= 0.5,
+///         );
+///     })
+///     .build();
+/// executor.run(&world, ());
+/// ```
+pub struct BatchSize<'a>(BatchSizeKind<'a>);
+
+#[cfg_attr(not(feature = "parallel"), allow(dead_code))]
+enum BatchSizeKind<'a> {
+    Fixed(u32),
+    Auto {
+        archetypes: Box<dyn Iterator<Item = &'a Archetype> + 'a>,
+        item_cost: Option<Duration>,
+    },
+}
+
+impl<'a> BatchSize<'a> {
+    /// Batches of given size, except for the last one in each archetype, which may be smaller;
+    /// size of 0 is treated as 1.
+    pub fn fixed(size: u32) -> Self {
+        BatchSize(BatchSizeKind::Fixed(size))
+    }
+
+    /// Batches sized automatically, given archetypes of the world being queried; see
+    /// [`SystemContext::archetypes()`](struct.SystemContext.html#method.archetypes)
+    /// and [`hecs::World::archetypes()`](../hecs/struct.World.html#method.archetypes).
+    ///
+    /// The archetypes are only iterated once the query is, skipping those it doesn't match.
+    pub fn auto<I>(archetypes: I) -> Self
+    where
+        I: IntoIterator<Item = &'a Archetype>,
+        I::IntoIter: 'a,
+    {
+        BatchSize(BatchSizeKind::Auto {
+            archetypes: Box::new(archetypes.into_iter()),
+            item_cost: None,
+        })
+    }
+
+    /// Makes automatically sized batches large enough for the overhead of scheduling them
+    /// to be negligible, given the time it takes to process a single item; the cost
+    /// is estimated by the caller, not measured. Has no effect on fixed sizes.
+    pub fn with_item_cost(mut self, cost: Duration) -> Self {
+        if let BatchSizeKind::Auto { item_cost, .. } = &mut self.0 {
+            *item_cost = Some(cost);
+        }
+        self
+    }
+
+    /// Returns the size the query should be iterated with, and, for each non-empty archetype
+    /// matched by the query, the number of resulting pieces in it and how many of them
+    /// should be processed as one batch.
+    #[cfg(feature = "parallel")]
+    fn plan<Q>(self) -> (u32, Vec<(u32, u32)>)
+    where
+        Q: Query,
+    {
+        let (archetypes, item_cost) = match self.0 {
+            BatchSizeKind::Fixed(size) => return (size.max(1), Vec::new()),
+            BatchSizeKind::Auto {
+                archetypes,
+                item_cost,
+            } => (archetypes, item_cost),
+        };
+        let lengths: Vec<u32> = archetypes
+            .filter(|archetype| !archetype.is_empty() && archetype.access::<Q>().is_some())
+            .map(|archetype| archetype.len())
+            .collect();
+        let tasks = (rayon::current_num_threads() as u32)
+            .saturating_mul(TASKS_PER_THREAD)
+            .max(1);
+        let min_batch = match item_cost {
+            Some(cost) => (BATCH_OVERHEAD.as_nanos() / cost.as_nanos().max(1))
+                .clamp(1, u32::MAX as u128) as u32,
+            None => MIN_AUTO_BATCH,
+        };
+        let batches: Vec<u32> = lengths
+            .iter()
+            .map(|length| length.div_ceil(tasks).max(min_batch))
+            .collect();
+        let total: u64 = lengths.iter().map(|&length| length as u64).sum();
+        let granule = batches.iter().copied().min().unwrap_or(1).max(
+            total
+                .div_ceil(tasks as u64 * MAX_GRANULES_PER_BATCH as u64)
+                .min(u32::MAX as u64) as u32,
+        );
+        let plan = lengths
+            .iter()
+            .zip(batches)
+            .map(|(length, batch)| (length.div_ceil(granule), (batch / granule).max(1)))
+            .collect();
+        (granule, plan)
+    }
+}
+
+impl From<u32> for BatchSize<'_> {
+    fn from(size: u32) -> Self {
+        BatchSize::fixed(size)
+    }
+}
 
 #[cfg_attr(not(feature = "parallel"), allow(unused_variables))]
 /// Distributes over a `rayon` thread pool the work of applying a function to items in a query.
 /// See [`hecs::QueryBorrow::iter_batched()`](../hecs/struct.QueryBorrow.html#method.iter_batched).
 ///
+/// The batch size is either a fixed `u32`, or a [`BatchSize`](struct.BatchSize.html),
+/// which can pick sizes automatically. All batches are enumerated before any work starts,
+/// and idle threads steal them from busy ones by splitting their remaining work in half.
+///
 /// If the default `parallel` feature is disabled the functionality is identical
 /// to `query_borrow.iter().for_each(for_each)`.
 ///
@@ -105,9 +261,9 @@ use hecs::{Entity, Fetch, Query, QueryBorrow};
 ///     executor.run(&world, &mut num_entities);
 /// });
 /// ```
-pub fn batch<'query, 'world, Q, F>(
+pub fn batch<'query, 'world, 'size, Q, F>(
     query_borrow: &'query mut QueryBorrow<'world, Q>,
-    batch_size: u32,
+    batch_size: impl Into<BatchSize<'size>>,
     for_each: F,
 ) where
     Q: Query + Send + Sync + 'query,
@@ -115,11 +271,31 @@ pub fn batch<'query, 'world, Q, F>(
 {
     #[cfg(feature = "parallel")]
     {
-        use rayon::prelude::{ParallelBridge, ParallelIterator};
-        query_borrow
-            .iter_batched(batch_size)
-            .par_bridge()
-            .for_each(|batch| batch.for_each(|(entity, components)| for_each(entity, components)));
+        let (granule, plan) = batch_size.into().plan::<Q>();
+        // All pieces of the query are enumerated up front, so that `rayon` can split them
+        // recursively between threads, instead of handing them out one by one.
+        let mut granules: Vec<_> = query_borrow.iter_batched(granule).collect();
+        let mut batches = Vec::with_capacity(granules.len());
+        let mut rest = granules.as_mut_slice();
+        for (mut remaining, per_batch) in plan {
+            while remaining > 0 && !rest.is_empty() {
+                let taken = per_batch.min(remaining);
+                let length = (taken as usize).min(rest.len());
+                let (batch, tail) = std::mem::take(&mut rest).split_at_mut(length);
+                batches.push(batch);
+                rest = tail;
+                remaining -= taken;
+            }
+        }
+        // Fixed sizes have no plan; should a plan not cover the whole query,
+        // the rest is still processed.
+        batches.extend(rest.chunks_mut(1));
+        batches.into_par_iter().for_each(|batch| {
+            batch
+                .iter_mut()
+                .flatten()
+                .for_each(|(entity, components)| for_each(entity, components))
+        });
     }
     #[cfg(not(feature = "parallel"))]
     {
@@ -128,3 +304,369 @@ pub fn batch<'query, 'world, Q, F>(
             .for_each(|(entity, components)| for_each(entity, components));
     }
 }
+
+/// Distributes over a `rayon` thread pool the work of applying a function to items
+/// of a query against a list of entities, prepared with
+/// [`SystemContext::query_entities()`](struct.SystemContext.html#method.query_entities).
+///
+/// The function is called once for each entity, with `None` in place of the item
+/// if the entity doesn't exist or doesn't match the query.
+///
+/// If the default `parallel` feature is disabled the entities are processed in the order
+/// they were listed, in a single thread.
+pub fn batch_entities<'query, 'world, Q, F>(
+    query_entities: &'query mut QueryEntities<'world, Q>,
+    for_each: F,
+) where
+    Q: Query + Send + Sync + 'query,
+    <<Q as Query>::Fetch as Fetch<'query>>::Item: Send,
+    F: Fn(Entity, Option<<<Q as Query>::Fetch as Fetch<'query>>::Item>) + Send + Sync,
+{
+    let items = query_entities.borrows.items();
+    #[cfg(feature = "parallel")]
+    items
+        .into_par_iter()
+        .for_each(|(entity, item)| for_each(entity, item));
+    #[cfg(not(feature = "parallel"))]
+    items
+        .into_iter()
+        .for_each(|(entity, item)| for_each(entity, item));
+}
+
+#[cfg_attr(not(feature = "parallel"), allow(unused_variables))]
+/// Creates a [`rayon::iter::ParallelIterator`][pi] over items of a query, split into batches
+/// of given size; see [`hecs::QueryBorrow::iter_batched()`][ib].
+///
+/// Unlike [`yaks::batch()`](fn.batch.html), this allows using adapters like
+/// `map()`, `filter()`, `reduce()`, `sum()`, or `for_each_init()`. Same as with `batch()`,
+/// the work is distributed over whichever thread pool the caller is running on.
+///
+/// If the default `parallel` feature is disabled the returned value is a plain
+/// [`Iterator`](https://doc.rust-lang.org/std/iter/trait.Iterator.html) over the query,
+/// and `batch_size` is ignored; adapters common to both traits will work either way.
+///
+/// [pi]: ../rayon/iter/trait.ParallelIterator.html
+/// [ib]: ../hecs/struct.QueryBorrow.html#method.iter_batched
+///
+/// # Example
+/// ```rust
+/// # use yaks::{Executor, QueryMarker};
+/// # #[cfg(feature = "parallel")]
+/// # use rayon::iter::ParallelIterator;
+/// struct Velocity(f32);
+///
+/// let mut world = hecs::World::new();
+/// world.spawn_batch((0..64).map(|index| (Velocity(index as f32),)));
+/// let mut fastest = 0.0;
+/// let mut executor = Executor::<(f32,)>::builder()
+///     .system(|context, fastest: &mut f32, query: QueryMarker<&Velocity>| {
+///         *fastest = yaks::par_iter(&mut context.query(query), 16)
+///             .map(|(_entity, velocity)| velocity.0)
+///             .max_by(|a, b| a.partial_cmp(b).unwrap())
+///             .unwrap_or(0.0);
+///     })
+///     .build();
+/// executor.run(&world, &mut fastest);
+/// assert_eq!(fastest, 63.0);
+/// ```
+pub fn par_iter<'query, 'world, Q>(
+    query_borrow: &'query mut QueryBorrow<'world, Q>,
+    batch_size: u32,
+) -> ParIter<'query, Q>
+where
+    Q: Query + Send + Sync + 'query,
+{
+    #[cfg(feature = "parallel")]
+    {
+        ParIter {
+            batches: query_borrow.iter_batched(batch_size.max(1)).collect(),
+        }
+    }
+    #[cfg(not(feature = "parallel"))]
+    {
+        ParIter {
+            iter: query_borrow.iter(),
+        }
+    }
+}
+
+/// Iterator over items of a query, split into batches; see [`yaks::par_iter()`](fn.par_iter.html).
+///
+/// Implements [`rayon::iter::ParallelIterator`](../rayon/iter/trait.ParallelIterator.html)
+/// if the default `parallel` feature is enabled, and
+/// [`Iterator`](https://doc.rust-lang.org/std/iter/trait.Iterator.html) otherwise.
+pub struct ParIter<'query, Q>
+where
+    Q: Query,
+{
+    #[cfg(feature = "parallel")]
+    batches: Vec<<BatchedIter<'query, Q> as Iterator>::Item>,
+    #[cfg(not(feature = "parallel"))]
+    iter: QueryIter<'query, Q>,
+}
+
+#[cfg(feature = "parallel")]
+impl<'query, Q> RayonParallelIterator for ParIter<'query, Q>
+where
+    Q: Query + Send + Sync + 'query,
+    <<Q as Query>::Fetch as Fetch<'query>>::Item: Send,
+{
+    type Item = (Entity, <<Q as Query>::Fetch as Fetch<'query>>::Item);
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        self.batches
+            .into_par_iter()
+            .flat_map_iter(|batch| batch)
+            .drive_unindexed(consumer)
+    }
+}
+
+#[cfg(not(feature = "parallel"))]
+impl<'query, Q> Iterator for ParIter<'query, Q>
+where
+    Q: Query + 'query,
+{
+    type Item = (Entity, <<Q as Query>::Fetch as Fetch<'query>>::Item);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+/// Reduces items of a query to a single value, distributing the work over a `rayon` thread
+/// pool, such that the result is identical regardless of the number of threads or the order
+/// in which they pick up work.
+///
+/// The query is split into batches of given size, in order of archetypes and of entities'
+/// positions within them (see
+/// [`hecs::QueryBorrow::iter_batched()`](../hecs/struct.QueryBorrow.html#method.iter_batched)).
+/// Each batch is folded sequentially, starting from a value created by `identity`, and
+/// the results are merged with `combine` pairwise, in a balanced tree whose shape depends
+/// only on the number of batches. Hence, as long as the world is populated in the same way,
+/// floating point sums and other non-associative reductions are bit-identical between runs.
+///
+/// If the default `parallel` feature is disabled the batches are folded in a single thread,
+/// producing the same result.
+///
+/// # Example
+/// ```rust
+/// struct Mass(f32);
+///
+/// let mut world = hecs::World::new();
+/// world.spawn_batch((0..1000).map(|index| (Mass(index as f32 * 0.1),)));
+/// let total = yaks::batch_reduce(
+///     &mut world.query::<&Mass>(),
+///     64,
+///     || 0.0f32,
+///     |total, _entity, mass| total + mass.0,
+///     |left, right| left + right,
+/// );
+/// let again = yaks::batch_reduce(
+///     &mut world.query::<&Mass>(),
+///     64,
+///     || 0.0f32,
+///     |total, _entity, mass| total + mass.0,
+///     |left, right| left + right,
+/// );
+/// assert_eq!(total.to_bits(), again.to_bits());
+/// ```
+pub fn batch_reduce<'query, 'world, Q, T, Identity, Fold, Combine>(
+    query_borrow: &'query mut QueryBorrow<'world, Q>,
+    batch_size: u32,
+    identity: Identity,
+    fold: Fold,
+    combine: Combine,
+) -> T
+where
+    Q: Query + Send + Sync + 'query,
+    T: Send,
+    Identity: Fn() -> T + Send + Sync,
+    Fold: Fn(T, Entity, <<Q as Query>::Fetch as Fetch<'query>>::Item) -> T + Send + Sync,
+    Combine: Fn(T, T) -> T,
+{
+    let batches: Vec<_> = query_borrow.iter_batched(batch_size.max(1)).collect();
+    let fold_batch = |batch: <BatchedIter<'query, Q> as Iterator>::Item| {
+        batch.fold(identity(), |accumulator, (entity, components)| {
+            fold(accumulator, entity, components)
+        })
+    };
+    #[cfg(feature = "parallel")]
+    let mut partials: Vec<T> = batches.into_par_iter().map(fold_batch).collect();
+    #[cfg(not(feature = "parallel"))]
+    let mut partials: Vec<T> = batches.into_iter().map(fold_batch).collect();
+    while partials.len() > 1 {
+        let mut pairs = partials.into_iter();
+        let mut combined = Vec::with_capacity(pairs.len().div_ceil(2));
+        while let Some(left) = pairs.next() {
+            combined.push(match pairs.next() {
+                Some(right) => combine(left, right),
+                None => left,
+            });
+        }
+        partials = combined;
+    }
+    partials.pop().unwrap_or_else(identity)
+}
+
+/// Calls a function with every unordered pair of distinct items in a query exactly once,
+/// distributing the work over a `rayon` thread pool.
+///
+/// The pairs form a triangle, which is split into square tiles of `tile_size` by `tile_size`
+/// items, each handled by a single thread; tile size of 0 is treated as 1. The items are
+/// only ever accessed immutably, and so may be visited from several threads at once.
+///
+/// Within a pair, the first item always precedes the second one in the order of
+/// [`hecs::QueryBorrow::iter()`](../hecs/struct.QueryBorrow.html#method.iter).
+///
+/// If the default `parallel` feature is disabled the tiles are processed in a single thread.
+///
+/// # Example
+/// ```rust
+/// # use parking_lot::Mutex;
+/// struct Position(f32);
+///
+/// let mut world = hecs::World::new();
+/// world.spawn_batch((0..100).map(|index| (Position(index as f32),)));
+/// let close = Mutex::new(0);
+/// yaks::batch_pairs(
+///     &mut world.query::<&Position>(),
+///     16,
+///     |_, first, _, second| {
+///         if (first.0 - second.0).abs() < 1.5 {
+///             *close.lock() += 1;
+///         }
+///     },
+/// );
+/// assert_eq!(*close.lock(), 99);
+/// ```
+pub fn batch_pairs<'query, 'world, Q, F>(
+    query_borrow: &'query mut QueryBorrow<'world, Q>,
+    tile_size: u32,
+    for_each: F,
+) where
+    Q: Query + Send + Sync + 'query,
+    <<Q as Query>::Fetch as Fetch<'query>>::Item: Sync,
+    F: Fn(
+            Entity,
+            &<<Q as Query>::Fetch as Fetch<'query>>::Item,
+            Entity,
+            &<<Q as Query>::Fetch as Fetch<'query>>::Item,
+        ) + Send
+        + Sync,
+{
+    batch_pairs_with(
+        query_borrow,
+        tile_size,
+        || (),
+        |_, first_entity, first, second_entity, second| {
+            for_each(first_entity, first, second_entity, second)
+        },
+    );
+}
+
+/// Same as [`yaks::batch_pairs()`](fn.batch_pairs.html), but also passes to the function
+/// an output buffer owned by the current thread, created by `init` when first needed.
+///
+/// Returns the buffers of all threads that processed any pairs, in order of their indices
+/// in the thread pool; the order of values within each buffer is unspecified.
+///
+/// # Example
+/// ```rust
+/// struct Position(f32);
+///
+/// let mut world = hecs::World::new();
+/// world.spawn_batch((0..100).map(|index| (Position(index as f32),)));
+/// let mut close: Vec<_> = yaks::batch_pairs_with(
+///     &mut world.query::<&Position>(),
+///     16,
+///     Vec::new,
+///     |close, first_entity, first, second_entity, second| {
+///         if (first.0 - second.0).abs() < 1.5 {
+///             close.push((first_entity, second_entity));
+///         }
+///     },
+/// )
+/// .into_iter()
+/// .flatten()
+/// .collect();
+/// assert_eq!(close.len(), 99);
+/// ```
+pub fn batch_pairs_with<'query, 'world, Q, T, Init, F>(
+    query_borrow: &'query mut QueryBorrow<'world, Q>,
+    tile_size: u32,
+    init: Init,
+    for_each: F,
+) -> Vec<T>
+where
+    Q: Query + Send + Sync + 'query,
+    <<Q as Query>::Fetch as Fetch<'query>>::Item: Sync,
+    T: Send,
+    Init: Fn() -> T + Send + Sync,
+    F: Fn(
+            &mut T,
+            Entity,
+            &<<Q as Query>::Fetch as Fetch<'query>>::Item,
+            Entity,
+            &<<Q as Query>::Fetch as Fetch<'query>>::Item,
+        ) + Send
+        + Sync,
+{
+    let items: Vec<_> = query_borrow.iter().collect();
+    let tile_size = tile_size.max(1) as usize;
+    let rows = items.len().div_ceil(tile_size);
+    #[cfg(feature = "parallel")]
+    let buffers = rayon::current_num_threads() + 1;
+    #[cfg(not(feature = "parallel"))]
+    let buffers = 1;
+    let buffers: Vec<Mutex<Option<T>>> = (0..buffers).map(|_| Mutex::new(None)).collect();
+    let process_tile = |tile: usize| {
+        let (row, column) = triangle_position(tile);
+        let tile_items = |index: usize| {
+            let start = index * tile_size;
+            &items[start..(start + tile_size).min(items.len())]
+        };
+        let mut buffer = buffers[partial_index(buffers.len())].lock();
+        let buffer = buffer.get_or_insert_with(&init);
+        let (row_items, column_items) = (tile_items(row), tile_items(column));
+        for (index, (first_entity, first)) in column_items.iter().enumerate() {
+            // Tiles on the diagonal hold each pair twice, and also pairs of items with themselves.
+            let second_items = if row == column {
+                &row_items[index + 1..]
+            } else {
+                row_items
+            };
+            for (second_entity, second) in second_items {
+                for_each(buffer, *first_entity, first, *second_entity, second);
+            }
+        }
+    };
+    let tiles = rows * (rows + 1) / 2;
+    #[cfg(feature = "parallel")]
+    (0..tiles).into_par_iter().for_each(process_tile);
+    #[cfg(not(feature = "parallel"))]
+    (0..tiles).for_each(process_tile);
+    buffers
+        .into_iter()
+        .filter_map(|buffer| buffer.into_inner())
+        .collect()
+}
+
+/// Returns row and column of a tile in a lower triangle of tiles, including the diagonal,
+/// numbered row by row.
+fn triangle_position(tile: usize) -> (usize, usize) {
+    let mut row = (((8.0 * tile as f64 + 1.0).sqrt() - 1.0) / 2.0) as usize;
+    while row * (row + 1) / 2 > tile {
+        row -= 1;
+    }
+    while (row + 1) * (row + 2) / 2 <= tile {
+        row += 1;
+    }
+    (row, tile - row * (row + 1) / 2)
+}
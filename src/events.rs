@@ -0,0 +1,282 @@
+use hecs::World;
+
+use crate::{resource::Contains, SystemAccess, SystemParam};
+
+/// A double-buffered queue of events of type `E`, to be used as a resource.
+///
+/// Systems send events with an [`EventWriter`](struct.EventWriter.html) and receive them
+/// with an [`EventReader`](struct.EventReader.html); each reader keeps track of events
+/// it has already seen. Events are kept for two updates, and an executor with any writers
+/// or readers of the queue calls [`::update()`](#method.update) at the end of each of it's
+/// runs, so that a reader running once per run will see every event exactly once regardless
+/// of whether it ran before or after the writer. Each executor using the queue updates it,
+/// so writers and readers of a queue should be kept in the same executor.
+///
+/// Since the executor modifies the queue, it can't be given as a shared reference.
+///
+/// Since writers require mutable access to the queue, systems writing events of the same
+/// type will not run concurrently with each other or with readers of that type.
+///
+/// # Example
+/// ```rust
+/// # use yaks::{EventReader, EventWriter, Events, Executor};
+/// # let world = hecs::World::new();
+/// struct Collision(u32);
+///
+/// let mut collisions = Events::<Collision>::new();
+/// let mut total = 0u32;
+/// let mut executor = Executor::<(Events<Collision>, u32)>::builder()
+///     .system(|_context, mut writer: EventWriter<Collision>, _queries: ()| {
+///         writer.send(Collision(1));
+///     })
+///     .system(|_context, (mut reader, total): (EventReader<Collision>, &mut u32), _queries: ()| {
+///         for collision in reader.iter() {
+///             *total += collision.0;
+///         }
+///     })
+///     .build();
+/// for _ in 0..3 {
+///     executor.run(&world, (&mut collisions, &mut total));
+/// }
+/// # drop(executor);
+/// # // The reader may run before or after the writer.
+/// # assert!(total == 2 || total == 3);
+/// ```
+pub struct Events<E> {
+    previous: Vec<E>,
+    current: Vec<E>,
+    previous_start: usize,
+    current_start: usize,
+}
+
+impl<E> Events<E> {
+    /// Creates an empty queue.
+    pub fn new() -> Self {
+        Self {
+            previous: Vec::new(),
+            current: Vec::new(),
+            previous_start: 0,
+            current_start: 0,
+        }
+    }
+
+    /// Adds an event to the queue.
+    pub fn send(&mut self, event: E) {
+        self.current.push(event);
+    }
+
+    /// Drops events sent before the previous update, making room for new ones.
+    pub fn update(&mut self) {
+        std::mem::swap(&mut self.previous, &mut self.current);
+        self.current.clear();
+        self.previous_start = self.current_start;
+        self.current_start += self.previous.len();
+    }
+
+    /// Removes all events from the queue; readers will not see them.
+    pub fn clear(&mut self) {
+        self.update();
+        self.update();
+    }
+
+    /// Returns `true` if there are no events in the queue.
+    pub fn is_empty(&self) -> bool {
+        self.previous.is_empty() && self.current.is_empty()
+    }
+
+    fn end(&self) -> usize {
+        self.current_start + self.current.len()
+    }
+
+    fn iter_from(&self, cursor: usize) -> impl Iterator<Item = &E> {
+        let previous = cursor
+            .saturating_sub(self.previous_start)
+            .min(self.previous.len());
+        let current = cursor
+            .saturating_sub(self.current_start)
+            .min(self.current.len());
+        self.previous[previous..]
+            .iter()
+            .chain(self.current[current..].iter())
+    }
+}
+
+impl<E> Default for Events<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Sends events into an [`Events`](struct.Events.html) queue; used as
+/// a [`SystemParam`](trait.SystemParam.html) in the resources argument of a system.
+///
+/// Requires the executor's resources to contain `Events<E>`, which it borrows mutably.
+pub struct EventWriter<'a, E>(&'a mut Events<E>);
+
+impl<'a, E> EventWriter<'a, E> {
+    /// Wraps a queue, for calling systems as plain functions;
+    /// see [`System`](trait.System.html).
+    pub fn new(events: &'a mut Events<E>) -> Self {
+        Self(events)
+    }
+
+    /// Adds an event to the queue.
+    pub fn send(&mut self, event: E) {
+        self.0.send(event);
+    }
+
+    /// Adds all events from the iterator to the queue.
+    pub fn send_batch(&mut self, events: impl IntoIterator<Item = E>) {
+        self.0.current.extend(events);
+    }
+}
+
+/// Has the queue updated when the executor's resource cells are dropped.
+fn update_events<Cells, M0, E>(resources: *const ())
+where
+    Cells: Contains<Events<E>, M0>,
+{
+    unsafe { &*(resources as *const Cells) }.finalize_with(Events::update);
+}
+
+impl<'a, Cells, M0, E> SystemParam<'a, Cells, M0> for EventWriter<'a, E>
+where
+    Cells: Contains<Events<E>, M0>,
+    &'a mut Events<E>: SystemParam<'a, Cells, M0>,
+{
+    type State = <&'a mut Events<E> as SystemParam<'a, Cells, M0>>::State;
+
+    fn init_state() -> Self::State {
+        <&'a mut Events<E> as SystemParam<'a, Cells, M0>>::init_state()
+    }
+
+    fn declare_access(access: &mut SystemAccess) {
+        <&'a mut Events<E> as SystemParam<'a, Cells, M0>>::declare_access(access);
+        access
+            .finalizers
+            .push((Cells::index(), update_events::<Cells, M0, E>));
+    }
+
+    fn fetch(state: &'a mut Self::State, resources: &'a Cells, world: &'a World) -> Self {
+        EventWriter(<&'a mut Events<E> as SystemParam<'a, Cells, M0>>::fetch(
+            state, resources, world,
+        ))
+    }
+
+    unsafe fn release(resources: &'a Cells) {
+        <&'a mut Events<E> as SystemParam<'a, Cells, M0>>::release(resources);
+    }
+}
+
+/// Receives events from an [`Events`](struct.Events.html) queue; used as
+/// a [`SystemParam`](trait.SystemParam.html) in the resources argument of a system.
+///
+/// Requires the executor's resources to contain `Events<E>`, which it borrows immutably.
+/// Each system has it's own cursor, tracking which events it has already seen.
+pub struct EventReader<'a, E> {
+    events: &'a Events<E>,
+    cursor: &'a mut usize,
+}
+
+impl<'a, E> EventReader<'a, E> {
+    /// Wraps a queue and a cursor, for calling systems as plain functions;
+    /// see [`System`](trait.System.html). Cursor should start at `0`, and be kept
+    /// between calls.
+    pub fn new(events: &'a Events<E>, cursor: &'a mut usize) -> Self {
+        Self { events, cursor }
+    }
+
+    /// Iterates over events that this reader has not seen yet, marking them as seen.
+    pub fn iter(&mut self) -> impl Iterator<Item = &'a E> {
+        let events = self.events;
+        let cursor = std::mem::replace(&mut *self.cursor, events.end());
+        events.iter_from(cursor)
+    }
+
+    /// Returns `true` if there are no events that this reader has not seen yet.
+    pub fn is_empty(&self) -> bool {
+        self.events.iter_from(*self.cursor).next().is_none()
+    }
+}
+
+impl<'a, Cells, M0, E> SystemParam<'a, Cells, M0> for EventReader<'a, E>
+where
+    Cells: Contains<Events<E>, M0>,
+    &'a Events<E>: SystemParam<'a, Cells, M0>,
+{
+    type State = (<&'a Events<E> as SystemParam<'a, Cells, M0>>::State, usize);
+
+    fn init_state() -> Self::State {
+        (
+            <&'a Events<E> as SystemParam<'a, Cells, M0>>::init_state(),
+            0,
+        )
+    }
+
+    fn declare_access(access: &mut SystemAccess) {
+        <&'a Events<E> as SystemParam<'a, Cells, M0>>::declare_access(access);
+        access
+            .finalizers
+            .push((Cells::index(), update_events::<Cells, M0, E>));
+        access.outputs.push(Cells::index());
+    }
+
+    fn fetch(state: &'a mut Self::State, resources: &'a Cells, world: &'a World) -> Self {
+        let (state, cursor) = state;
+        EventReader {
+            events: <&'a Events<E> as SystemParam<'a, Cells, M0>>::fetch(state, resources, world),
+            cursor,
+        }
+    }
+
+    unsafe fn release(resources: &'a Cells) {
+        <&'a Events<E> as SystemParam<'a, Cells, M0>>::release(resources);
+    }
+}
+
+#[test]
+fn smoke_test() {
+    let mut events = Events::new();
+    let mut cursor_a = 0;
+    let mut cursor_b = 0;
+    events.send(0);
+    events.send(1);
+    assert_eq!(
+        EventReader::new(&events, &mut cursor_a)
+            .iter()
+            .copied()
+            .collect::<Vec<_>>(),
+        vec![0, 1]
+    );
+    events.update();
+    EventWriter::new(&mut events).send_batch(vec![2, 3]);
+    assert_eq!(
+        EventReader::new(&events, &mut cursor_a)
+            .iter()
+            .copied()
+            .collect::<Vec<_>>(),
+        vec![2, 3]
+    );
+    assert_eq!(
+        EventReader::new(&events, &mut cursor_b)
+            .iter()
+            .copied()
+            .collect::<Vec<_>>(),
+        vec![0, 1, 2, 3]
+    );
+    events.update();
+    events.update();
+    events.send(4);
+    assert!(!EventReader::new(&events, &mut cursor_a).is_empty());
+    assert_eq!(
+        EventReader::new(&events, &mut cursor_a)
+            .iter()
+            .copied()
+            .collect::<Vec<_>>(),
+        vec![4]
+    );
+    assert!(EventReader::new(&events, &mut cursor_a).is_empty());
+    events.clear();
+    assert!(events.is_empty());
+    assert!(EventReader::new(&events, &mut cursor_b).is_empty());
+}
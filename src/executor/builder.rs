@@ -1,13 +1,16 @@
-use std::{collections::HashMap, fmt::Debug, hash::Hash};
+use hecs::{ArchetypesGeneration, World};
+use std::{collections::HashMap, fmt::Debug, hash::Hash, marker::PhantomData, time::Instant};
 
 #[cfg(feature = "parallel")]
-use hecs::World;
-
-use super::SystemClosure;
-use crate::{Executor, Fetch, QueryBundle, ResourceTuple, SystemContext, SystemId};
+use super::ArchetypeWriter;
+use super::{fetching_for, SystemClosure};
+use crate::{
+    system_param::Finalizer, Executor, QueryBundle, ResourceTuple, SystemAccess, SystemContext,
+    SystemId, SystemParam,
+};
 
 #[cfg(feature = "parallel")]
-use crate::{ArchetypeSet, BorrowSet, BorrowTypeSet, TypeSet};
+use crate::{ArchetypeSet, BorrowFieldSet, BorrowSet, BorrowTypeSet, TypeSet};
 
 /// Container for parsed systems and their metadata;
 /// destructured in concrete executors' build functions.
@@ -20,9 +23,50 @@ where
     #[cfg(feature = "parallel")]
     pub resource_set: BorrowSet,
     #[cfg(feature = "parallel")]
+    pub resource_field_set: BorrowFieldSet,
+    #[cfg(feature = "parallel")]
     pub component_type_set: BorrowTypeSet,
     #[cfg(feature = "parallel")]
-    pub archetype_writer: Box<dyn Fn(&World, &mut ArchetypeSet) + Send>,
+    pub archetype_writer: Box<ArchetypeWriter>,
+    #[cfg(feature = "resources-interop")]
+    pub required_resources: Vec<usize>,
+    pub inputs: Vec<usize>,
+    pub outputs: Vec<usize>,
+    pub finalizers: Vec<(usize, Finalizer)>,
+    pub handle: Option<String>,
+    pub name: String,
+}
+
+/// Releases the borrows of a system's resources when dropped, so that they're released
+/// (and any locks unlocked) even if the system panics.
+struct Release<'a, Param, Cells, Markers>
+where
+    Param: SystemParam<'a, Cells, Markers>,
+{
+    resources: &'a Cells,
+    phantom_data: PhantomData<fn() -> (Param, Markers)>,
+}
+
+impl<'a, Param, Cells, Markers> Release<'a, Param, Cells, Markers>
+where
+    Param: SystemParam<'a, Cells, Markers>,
+{
+    fn new(resources: &'a Cells) -> Self {
+        Self {
+            resources,
+            phantom_data: PhantomData,
+        }
+    }
+}
+
+impl<'a, Param, Cells, Markers> Drop for Release<'a, Param, Cells, Markers>
+where
+    Param: SystemParam<'a, Cells, Markers>,
+{
+    fn drop(&mut self) {
+        // Created right after the parameter is fetched, dropped after it's consumed.
+        unsafe { Param::release(self.resources) };
+    }
 }
 
 /// A builder for [`Executor`](struct.Executor.html) (and the only way of creating one).
@@ -41,20 +85,36 @@ where
     Resources: ResourceTuple,
     Handle: Eq + Hash,
 {
+    /// Wraps the closure, recording its access and name; the latter is the `Debug`
+    /// representation of the handle if given, or the type name of the closure.
     fn box_system<'a, Closure, ResourceRefs, Queries, Markers>(
         mut closure: Closure,
+        handle: Option<String>,
     ) -> System<'closures, Resources>
     where
         Resources::Wrapped: 'a,
         Closure: FnMut(SystemContext<'a>, ResourceRefs, Queries) + Send + Sync + 'closures,
-        ResourceRefs: Fetch<'a, Resources::Wrapped, Markers> + 'a,
+        ResourceRefs: SystemParam<'a, Resources::Wrapped, Markers> + 'a,
         Queries: QueryBundle,
     {
+        let name = handle
+            .clone()
+            .unwrap_or_else(|| std::any::type_name::<Closure>().to_string());
+        let mut state = ResourceRefs::init_state();
+        let mut last_run: Option<Instant> = None;
+        let closure_name = name.clone();
         let closure = Box::new(
-            move |context: SystemContext<'a>, resources: &'a Resources::Wrapped| {
-                let fetched = ResourceRefs::fetch(resources);
+            move |mut context: SystemContext<'a>, resources: &'a Resources::Wrapped| {
+                let now = Instant::now();
+                context.delta = last_run.replace(now).map(|last_run| now - last_run);
+                // The state outlives the fetched value, which is dropped before this returns.
+                let state = unsafe { &mut *(&mut state as *mut ResourceRefs::State) };
+                let world = context.world;
+                let fetched = fetching_for(&closure_name, move || {
+                    ResourceRefs::fetch(state, resources, world)
+                });
+                let _release = Release::<ResourceRefs, _, _>::new(resources);
                 closure(context, fetched, Queries::markers());
-                unsafe { ResourceRefs::release(resources) };
             },
         );
         let closure = unsafe {
@@ -63,27 +123,38 @@ where
                 Box<dyn FnMut(SystemContext, &Resources::Wrapped) + Send + Sync + 'closures>,
             >(closure)
         };
+        let mut access = SystemAccess::new(Resources::LENGTH);
+        ResourceRefs::declare_access(&mut access);
         #[cfg(feature = "parallel")]
-        {
-            let mut resource_set = BorrowSet::with_capacity(Resources::LENGTH);
-            ResourceRefs::set_resource_bits(&mut resource_set);
-            let mut component_type_set = BorrowTypeSet::new();
-            Queries::insert_component_types(&mut component_type_set);
-            let archetype_writer = Box::new(|world: &World, archetype_set: &mut ArchetypeSet| {
-                Queries::set_archetype_bits(world, archetype_set)
-            });
-            System {
-                closure,
-                dependencies: vec![],
-                resource_set,
-                component_type_set,
-                archetype_writer,
-            }
-        }
-        #[cfg(not(feature = "parallel"))]
+        Queries::declare_access(&mut access);
+        #[cfg(feature = "parallel")]
+        let archetype_writer = {
+            let archetype_writers = std::mem::take(&mut access.archetype_writers);
+            Box::new(move |world: &World, archetype_set: &mut ArchetypeSet| {
+                archetype_set.reset(world);
+                for writer in &archetype_writers {
+                    writer(world, archetype_set);
+                }
+            })
+        };
         System {
             closure,
             dependencies: vec![],
+            #[cfg(feature = "parallel")]
+            resource_set: access.resource_set,
+            #[cfg(feature = "parallel")]
+            resource_field_set: access.resource_field_set,
+            #[cfg(feature = "parallel")]
+            component_type_set: access.component_type_set,
+            #[cfg(feature = "parallel")]
+            archetype_writer,
+            #[cfg(feature = "resources-interop")]
+            required_resources: access.required_resources,
+            inputs: access.inputs,
+            outputs: access.outputs,
+            finalizers: access.finalizers,
+            handle,
+            name,
         }
     }
 
@@ -92,10 +163,10 @@ where
     /// The system-to-be must return nothing and have these 3 arguments:
     /// - [`SystemContext`](struct.SystemContext.html),
     /// - any tuple (up to 16) or a single one of "resources": references or mutable references
-    /// to `Send + Sync` values not contained in a [`hecs::World`](../hecs/struct.World.html)
-    /// that the system will be accessing,
+    ///   to `Send + Sync` values not contained in a [`hecs::World`](../hecs/struct.World.html)
+    ///   that the system will be accessing, or other [`SystemParam`](trait.SystemParam.html)s,
     /// - any tuple (up to 16) or a single one of [`QueryMarker`](struct.QueryMarker.html) that
-    /// represent the queries the system will be making.
+    ///   represent the queries the system will be making.
     ///
     /// Additionally, closures may mutably borrow from their environment for the lifetime
     /// of the executor, but must be `Send + Sync`.
@@ -153,11 +224,11 @@ where
     where
         Resources::Wrapped: 'a,
         Closure: FnMut(SystemContext<'a>, ResourceRefs, Queries) + Send + Sync + 'closures,
-        ResourceRefs: Fetch<'a, Resources::Wrapped, Markers> + 'a,
+        ResourceRefs: SystemParam<'a, Resources::Wrapped, Markers> + 'a,
         Queries: QueryBundle,
     {
         let id = SystemId(self.systems.len());
-        let system = Self::box_system::<'a, Closure, ResourceRefs, Queries, Markers>(closure);
+        let system = Self::box_system::<'a, Closure, ResourceRefs, Queries, Markers>(closure, None);
         #[cfg(feature = "parallel")]
         {
             self.all_component_types
@@ -254,7 +325,7 @@ where
     where
         Resources::Wrapped: 'a,
         Closure: FnMut(SystemContext<'a>, ResourceRefs, Queries) + Send + Sync + 'closures,
-        ResourceRefs: Fetch<'a, Resources::Wrapped, Markers> + 'a,
+        ResourceRefs: SystemParam<'a, Resources::Wrapped, Markers> + 'a,
         Queries: QueryBundle,
         NewHandle: HandleConversion<Handle> + Debug,
     {
@@ -263,7 +334,10 @@ where
             panic!("system {:?} already exists", handle);
         }
         let id = SystemId(self.systems.len());
-        let system = Self::box_system::<'a, Closure, ResourceRefs, Queries, Markers>(closure);
+        let system = Self::box_system::<'a, Closure, ResourceRefs, Queries, Markers>(
+            closure,
+            Some(format!("{:?}", handle)),
+        );
         #[cfg(feature = "parallel")]
         {
             self.all_component_types
@@ -294,7 +368,7 @@ where
     /// # Panics
     /// This function will panic if:
     /// - given list of dependencies contains a handle that
-    /// doesn't correspond to any system in the builder.
+    ///   doesn't correspond to any system in the builder.
     pub fn system_with_deps<'a, Closure, ResourceRefs, Queries, Markers>(
         mut self,
         closure: Closure,
@@ -303,12 +377,13 @@ where
     where
         Resources::Wrapped: 'a,
         Closure: FnMut(SystemContext<'a>, ResourceRefs, Queries) + Send + Sync + 'closures,
-        ResourceRefs: Fetch<'a, Resources::Wrapped, Markers> + 'a,
+        ResourceRefs: SystemParam<'a, Resources::Wrapped, Markers> + 'a,
         Queries: QueryBundle,
         Handle: Eq + Hash + Debug,
     {
         let id = SystemId(self.systems.len());
-        let mut system = Self::box_system::<'a, Closure, ResourceRefs, Queries, Markers>(closure);
+        let mut system =
+            Self::box_system::<'a, Closure, ResourceRefs, Queries, Markers>(closure, None);
         #[cfg(feature = "parallel")]
         {
             self.all_component_types
@@ -316,16 +391,18 @@ where
             self.all_component_types
                 .extend(&system.component_type_set.mutable);
         }
-        system
-            .dependencies
-            .extend(dependencies.iter().map(|dep_handle| {
+        let resolved: Vec<_> = dependencies
+            .iter()
+            .map(|dep_handle| {
                 *self.handles.get(dep_handle).unwrap_or_else(|| {
                     panic!(
-                    "could not resolve dependencies of a handle-less system: no system {:?} found",
-                    dep_handle
-                )
+                        "could not resolve dependencies of system {}: no system {:?} found",
+                        system.name, dep_handle
+                    )
                 })
-            }));
+            })
+            .collect();
+        system.dependencies.extend(resolved);
         self.systems.insert(id, system);
         self
     }
@@ -344,7 +421,7 @@ where
     /// This function will panic if:
     /// - a system with given handle is already present in the builder,
     /// - given list of dependencies contains a handle that
-    /// doesn't correspond to any system in the builder,
+    ///   doesn't correspond to any system in the builder,
     /// - given handle appears in given list of dependencies.
     pub fn system_with_handle_and_deps<'a, Closure, ResourceRefs, Queries, Markers>(
         mut self,
@@ -355,7 +432,7 @@ where
     where
         Resources::Wrapped: 'a,
         Closure: FnMut(SystemContext<'a>, ResourceRefs, Queries) + Send + Sync + 'closures,
-        ResourceRefs: Fetch<'a, Resources::Wrapped, Markers> + 'a,
+        ResourceRefs: SystemParam<'a, Resources::Wrapped, Markers> + 'a,
         Queries: QueryBundle,
         Handle: Eq + Hash + Debug,
     {
@@ -366,7 +443,10 @@ where
             panic!("system {:?} depends on itself", handle);
         }
         let id = SystemId(self.systems.len());
-        let mut system = Self::box_system::<'a, Closure, ResourceRefs, Queries, Markers>(closure);
+        let mut system = Self::box_system::<'a, Closure, ResourceRefs, Queries, Markers>(
+            closure,
+            Some(format!("{:?}", handle)),
+        );
         #[cfg(feature = "parallel")]
         {
             self.all_component_types
@@ -389,9 +469,151 @@ where
         self
     }
 
+    /// Makes the system with given handle reactive: it will be skipped unless
+    /// it's running for the first time, the [`hecs::World`](../hecs/struct.World.html) has
+    /// new archetypes (see [`hecs::World::archetypes_generation()`][ag]), or any of
+    /// the system's inputs has changed since it's previous run observed them.
+    ///
+    /// [ag]: ../hecs/struct.World.html#method.archetypes_generation
+    ///
+    /// Inputs of a system are resources it borrows immutably, wholly or in part, and those
+    /// requested via [`Changed`](struct.Changed.html); a resource is changed if any system
+    /// borrows it mutably, even if nothing is written (see [`Executor::change_tick()`][ct]).
+    /// Changes made by the system itself to resources it doesn't also read are ignored.
+    ///
+    /// [ct]: struct.Executor.html#method.change_tick
+    ///
+    /// A skipped system is considered finished, and doesn't hold up systems depending on it.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use yaks::Executor;
+    /// # let world = hecs::World::new();
+    /// struct Layout(u32);
+    /// struct Window(u32);
+    ///
+    /// let mut executor = Executor::<(Layout, Window)>::builder()
+    ///     .system_with_handle(
+    ///         |_context, (layout, window): (&mut Layout, &Window), _queries: ()| {
+    ///             layout.0 += window.0;
+    ///         },
+    ///         "layout",
+    ///     )
+    ///     .reactive("layout")
+    ///     .build();
+    /// let mut layout = Layout(0);
+    /// let mut window = Window(1);
+    /// executor.run(&world, (&mut layout, &mut window));
+    /// executor.run(&world, (&mut layout, &mut window));
+    /// assert_eq!(layout.0, 1);
+    /// ```
+    ///
+    /// # Panics
+    /// This function will panic if there is no system with given handle in the builder.
+    pub fn reactive(self, handle: Handle) -> Self
+    where
+        Handle: Debug,
+    {
+        self.reactive_with(handle, |_| false)
+    }
+
+    /// Same as [`::reactive()`](#method.reactive), but the system will also run
+    /// whenever given closure returns `true`. The closure is called on every run
+    /// of the executor, even if the system would run regardless.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use yaks::Executor;
+    /// # use std::sync::atomic::{AtomicBool, Ordering};
+    /// # let world = hecs::World::new();
+    /// static NAVMESH_DIRTY: AtomicBool = AtomicBool::new(false);
+    ///
+    /// let mut rebuilds = 0;
+    /// let mut executor = Executor::<()>::builder()
+    ///     .system_with_handle(|_context, _resources: (), _queries: ()| rebuilds += 1, 0)
+    ///     .reactive_with(0, |_world| NAVMESH_DIRTY.swap(false, Ordering::Relaxed))
+    ///     .build();
+    /// executor.run(&world, ());
+    /// executor.run(&world, ());
+    /// NAVMESH_DIRTY.store(true, Ordering::Relaxed);
+    /// executor.run(&world, ());
+    /// drop(executor);
+    /// assert_eq!(rebuilds, 2);
+    /// ```
+    ///
+    /// # Panics
+    /// This function will panic if there is no system with given handle in the builder.
+    pub fn reactive_with<DirtyCheck>(mut self, handle: Handle, is_dirty: DirtyCheck) -> Self
+    where
+        Handle: Debug,
+        DirtyCheck: Fn(&World) -> bool + Send + Sync + 'closures,
+    {
+        let id = *self.handles.get(&handle).unwrap_or_else(|| {
+            panic!(
+                "cannot make system {:?} reactive: no such system found",
+                handle
+            )
+        });
+        let system = self
+            .systems
+            .get_mut(&id)
+            .expect("systems should be contiguous");
+        let mut inputs = std::mem::take(&mut system.inputs);
+        inputs.sort_unstable();
+        inputs.dedup();
+        let mut closure = std::mem::replace(&mut system.closure, Box::new(|_, _| ()));
+        // Change ticks of inputs and archetypes generation observed by the latest run
+        // in which the system wasn't skipped.
+        let mut observed: Option<(Vec<u64>, ArchetypesGeneration)> = None;
+        let change_tick = move |resources: &Resources::Wrapped, index: &usize| {
+            Resources::wrapped_ticks(resources, *index).1
+        };
+        system.closure = Box::new(
+            move |context: SystemContext, resources: &Resources::Wrapped| {
+                let generation = context.world.archetypes_generation();
+                let is_dirty = is_dirty(context.world);
+                let should_run = match &observed {
+                    None => true,
+                    Some((change_ticks, observed_generation)) => {
+                        *observed_generation != generation
+                            || inputs
+                                .iter()
+                                .zip(change_ticks)
+                                .any(|(index, observed)| change_tick(resources, index) > *observed)
+                            || is_dirty
+                    }
+                };
+                if should_run {
+                    let change_ticks = inputs
+                        .iter()
+                        .map(|index| change_tick(resources, index))
+                        .collect();
+                    observed = Some((change_ticks, generation));
+                    closure(context, resources);
+                }
+            },
+        );
+        self
+    }
+
     /// Consumes the builder and returns the finalized executor.
     pub fn build(self) -> Executor<'closures, Resources> {
-        Executor::build(self)
+        Executor::build(self, None)
+    }
+
+    /// Consumes the builder and returns the finalized executor, which will own given resources.
+    ///
+    /// Such an executor can be ran with [`Executor::run_owned()`][ro], and it's resources
+    /// accessed between runs with [`Executor::resource()`][r] and
+    /// [`Executor::resource_mut()`][rm]. It can still be ran with
+    /// [`Executor::run()`][run] and resources from elsewhere.
+    ///
+    /// [ro]: struct.Executor.html#method.run_owned
+    /// [r]: struct.Executor.html#method.resource
+    /// [rm]: struct.Executor.html#method.resource_mut
+    /// [run]: struct.Executor.html#method.run
+    pub fn build_with_resources(self, resources: Resources) -> Executor<'closures, Resources> {
+        Executor::build(self, Some(resources))
     }
 }
 
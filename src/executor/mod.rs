@@ -1,7 +1,7 @@
 use hecs::World;
-use std::collections::HashMap;
+use std::{cell::Cell, collections::HashMap, fmt};
 
-use crate::{RefExtractor, ResourceTuple, SystemContext};
+use crate::{system_param::Finalizer, Get, ResourceProvider, ResourceTuple, SystemContext};
 
 mod builder;
 
@@ -19,24 +19,83 @@ use sequential::ExecutorSequential;
 mod parallel;
 
 #[cfg(feature = "parallel")]
-use crate::TypeSet;
+use crate::{ArchetypeSet, TypeSet};
 #[cfg(feature = "parallel")]
 use parallel::ExecutorParallel;
 
 type SystemClosure<'closure, Cells> = dyn FnMut(SystemContext, &Cells) + Send + Sync + 'closure;
 
+#[cfg(feature = "parallel")]
+type ArchetypeWriter = dyn Fn(&World, &mut ArchetypeSet) + Send;
+
+static NOT_OWNED: &str = "executor does not own its resources";
+
 #[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
 pub struct SystemId(pub(crate) usize);
 
+/// Metadata of a run of an executor, shared by all of its systems.
+#[derive(Clone, Copy, Default)]
+pub(crate) struct RunInfo<'run> {
+    pub tick: u64,
+    /// `Debug` representations of handles of systems, indexed by their IDs.
+    pub handles: &'run [Option<String>],
+}
+
+impl<'run> RunInfo<'run> {
+    pub fn context(&self, id: SystemId, world: &'run World) -> SystemContext<'run> {
+        SystemContext {
+            system_id: Some(id),
+            world,
+            tick: self.tick,
+            handle: self.handles.get(id.0).and_then(Option::as_deref),
+            delta: None,
+        }
+    }
+}
+
+thread_local! {
+    /// Name of the system whose resources are being fetched on this thread, if any.
+    static FETCHING_FOR: Cell<Option<*const str>> = const { Cell::new(None) };
+}
+
+/// Runs the closure with `name` recorded as the system resources are being fetched for,
+/// so that borrow panics can point at it.
+pub(crate) fn fetching_for<T>(name: &str, closure: impl FnOnce() -> T) -> T {
+    struct Restore(Option<*const str>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            FETCHING_FOR.with(|current| current.set(self.0));
+        }
+    }
+
+    let _restore = Restore(FETCHING_FOR.with(|current| current.replace(Some(name as *const str))));
+    closure()
+}
+
+/// Displays the system resources are being fetched for on this thread, if any;
+/// appended to borrow panic messages.
+pub(crate) struct InSystem;
+
+impl fmt::Display for InSystem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match FETCHING_FOR.with(Cell::get) {
+            // The name outlives the fetch it is recorded for, see `fetching_for()`.
+            Some(name) => write!(f, " (in system {})", unsafe { &*name }),
+            None => Ok(()),
+        }
+    }
+}
+
 /// A sealed container for systems that may be executed in parallel.
 ///
 /// Systems can be any closure or function that return nothing and have these 3 arguments:
 /// - [`SystemContext`](struct.SystemContext.html),
 /// - any tuple (up to 16) or a single one of "resources": references or mutable references
-/// to `Send + Sync` values not contained in a [`hecs::World`](../hecs/struct.World.html)
-/// that the system will be accessing,
+///   to `Send + Sync` values not contained in a [`hecs::World`](../hecs/struct.World.html)
+///   that the system will be accessing, or other [`SystemParam`](trait.SystemParam.html)s,
 /// - any tuple (up to 16) or a single one of [`QueryMarker`](struct.QueryMarker.html) that
-/// represent the queries the system will be making.
+///   represent the queries the system will be making.
 ///
 /// Additionally, closures may mutably borrow from their environment for the lifetime `'closures`
 /// of the executor, but must be `Send + Sync`. If none of the systems make any borrows from the
@@ -46,7 +105,8 @@ pub struct SystemId(pub(crate) usize);
 /// tuples of the contained systems. Any type in `Resources` must appear no more than once,
 /// however, any number of systems in the executor may have either an immutable or a mutable
 /// reference of said type in their signature. For example: if any number of systems require
-/// a `&f32` or a `&mut f32`, `Resources` must contain `f32`.
+/// a `&f32` or a `&mut f32`, `Resources` must contain `f32`. Several instances of the same type
+/// can be told apart with [`Labeled`](struct.Labeled.html).
 ///
 /// It's possible to define an order of execution of the systems by building up a dependency
 /// graph when building the executor, see [`ExecutorBuilder::system_with_handle()`][swh].
@@ -67,6 +127,16 @@ where
     Resources: ResourceTuple,
 {
     pub(crate) borrows: Resources::BorrowTuple,
+    pub(crate) resources: Option<Resources>,
+    tick: u64,
+    handles: Vec<Option<String>>,
+    names: Vec<String>,
+    /// The first system that may modify each of the resources, if any.
+    writers: Vec<Option<SystemId>>,
+    /// Register finalizers with the resource cells at the start of each run.
+    finalizers: Vec<Finalizer>,
+    #[cfg(feature = "resources-interop")]
+    pub(crate) required_resources: Vec<bool>,
     #[cfg(feature = "parallel")]
     pub(crate) inner: ExecutorParallel<'closures, Resources>,
     #[cfg(not(feature = "parallel"))]
@@ -87,9 +157,49 @@ where
         }
     }
 
-    pub(crate) fn build<Handle>(builder: ExecutorBuilder<'closures, Resources, Handle>) -> Self {
+    pub(crate) fn build<Handle>(
+        builder: ExecutorBuilder<'closures, Resources, Handle>,
+        resources: Option<Resources>,
+    ) -> Self {
+        // Resources that at least one system can't do without.
+        #[cfg(feature = "resources-interop")]
+        let required_resources = {
+            let mut required_resources = vec![false; Resources::LENGTH];
+            for system in builder.systems.values() {
+                for index in &system.required_resources {
+                    required_resources[*index] = true;
+                }
+            }
+            required_resources
+        };
+        let mut handles = vec![None; builder.systems.len()];
+        let mut names = vec![String::new(); builder.systems.len()];
+        let mut writers = vec![None; Resources::LENGTH];
+        let mut finalizers = Vec::new();
+        for (id, system) in &builder.systems {
+            handles[id.0] = system.handle.clone();
+            names[id.0] = system.name.clone();
+            for index in &system.outputs {
+                let writer: &mut Option<SystemId> = &mut writers[*index];
+                *writer = Some(writer.map_or(*id, |writer| writer.min(*id)));
+            }
+            finalizers.extend(&system.finalizers);
+        }
+        finalizers.sort_unstable_by_key(|(index, _)| *index);
+        finalizers.dedup_by_key(|(index, _)| *index);
         Self {
             borrows: Resources::instantiate_borrows(),
+            resources,
+            tick: 0,
+            handles,
+            names,
+            writers,
+            finalizers: finalizers
+                .into_iter()
+                .map(|(_, finalizer)| finalizer)
+                .collect(),
+            #[cfg(feature = "resources-interop")]
+            required_resources,
             #[cfg(feature = "parallel")]
             inner: ExecutorParallel::build(builder),
             #[cfg(not(feature = "parallel"))]
@@ -155,8 +265,35 @@ where
     /// let mut executor = Executor::<()>::builder().build();
     /// executor.run(&world, ());
     /// ```
-    /// In the future, exclusivity requirement might be relaxed for resources that aren't mutated
-    /// by any of the systems, but doing that as of writing is unfeasible.
+    /// Resources that none of the systems borrow mutably may instead be given
+    /// as shared references, in any position of the tuple:
+    /// ```rust
+    /// # use yaks::Executor;
+    /// # let world = hecs::World::new();
+    /// let mut executor = Executor::<(f32, u32)>::builder()
+    ///     .system(|_context, (some_f32, some_u32): (&mut f32, &u32), _queries: ()| {
+    ///         *some_f32 += *some_u32 as f32;
+    ///     })
+    ///     .build();
+    /// let mut some_f32 = 0f32;
+    /// let some_u32 = 1u32;
+    /// executor.run(&world, (&mut some_f32, &some_u32));
+    /// ```
+    /// Resources may also be given as `Option<&mut R>`; a `None` will be fetched as `None`
+    /// by systems requesting `Option<&R>` or `Option<&mut R>`:
+    /// ```rust
+    /// # use yaks::Executor;
+    /// # let world = hecs::World::new();
+    /// let mut executor = Executor::<(f32, u32)>::builder()
+    ///     .system(|_context, (some_f32, some_u32): (&mut f32, Option<&u32>), _queries: ()| {
+    ///         *some_f32 += some_u32.map_or(0.0, |some_u32| *some_u32 as f32);
+    ///     })
+    ///     .build();
+    /// let mut some_f32 = 0f32;
+    /// executor.run(&world, (&mut some_f32, None));
+    /// ```
+    /// Other containers can be used as the `resources` argument by implementing
+    /// [`ResourceProvider`](trait.ResourceProvider.html) for them.
     ///
     /// This function can be called inside a
     /// [`rayon::ThreadPool::install()`](../rayon/struct.ThreadPool.html#method.install) block
@@ -189,16 +326,181 @@ where
     /// # Panics
     /// This function will panic if:
     /// - a system within the executor has resource requirements that are incompatible with itself,
-    /// e.g. `(&mut SomeResource, &SomeResource)`.
+    ///   e.g. `(&mut SomeResource, &SomeResource)`,
+    /// - a resource given as a shared reference may be modified by any of the systems
+    ///   (checked before any of the systems run),
+    /// - a resource given as `None` is required by any of the systems without an `Option`.
     ///
     /// Additionally, it *may* panic if:
     /// - a different [`hecs::World`](../hecs/struct.World.html) is supplied than
-    /// in a previous call, without first calling
-    /// [`::force_archetype_recalculation()`](#method.force_archetype_recalculation).
-    pub fn run<RefSource>(&mut self, world: &World, resources: RefSource)
+    ///   in a previous call, without first calling
+    ///   [`::force_archetype_recalculation()`](#method.force_archetype_recalculation).
+    pub fn run<Provider>(&mut self, world: &World, resources: Provider)
+    where
+        Provider: ResourceProvider<Resources>,
+    {
+        resources.provide_and_run(self, world);
+    }
+
+    /// Executes all of the contained systems once, using resources owned by the executor;
+    /// see [`::run()`](#method.run) and
+    /// [`ExecutorBuilder::build_with_resources()`][bwr].
+    ///
+    /// [bwr]: struct.ExecutorBuilder.html#method.build_with_resources
+    ///
+    /// # Example
+    /// ```rust
+    /// # use yaks::Executor;
+    /// # let world = hecs::World::new();
+    /// let mut executor = Executor::<(f32, u32)>::builder()
+    ///     .system(|_context, (some_f32, some_u32): (&mut f32, &u32), _queries: ()| {
+    ///         *some_f32 += *some_u32 as f32;
+    ///     })
+    ///     .build_with_resources((0.0, 1));
+    /// executor.run_owned(&world);
+    /// *executor.resource_mut::<u32, _>() = 2;
+    /// executor.run_owned(&world);
+    /// assert_eq!(*executor.resource::<f32, _>(), 3.0);
+    /// ```
+    ///
+    /// # Panics
+    /// This function will panic if:
+    /// - the executor does not own its resources,
+    /// - for any of the reasons [`::run()`](#method.run) would.
+    pub fn run_owned(&mut self, world: &World) {
+        let wrapped = self
+            .resources
+            .as_mut()
+            .expect(NOT_OWNED)
+            .wrap_owned(&mut self.borrows);
+        self.run_wrapped(world, wrapped);
+    }
+
+    pub(crate) fn run_wrapped(&mut self, world: &World, wrapped: Resources::Wrapped) {
+        for (index, writer) in self.writers.iter().enumerate() {
+            if let Some(writer) = writer {
+                assert!(
+                    !Resources::wrapped_is_shared(&wrapped, index),
+                    "cannot run the executor: {} was given as a shared reference, \
+                    but system {} may modify it",
+                    Resources::type_name(index),
+                    self.names[writer.0]
+                );
+            }
+        }
+        for finalizer in &self.finalizers {
+            finalizer(&wrapped as *const Resources::Wrapped as *const ());
+        }
+        let run_info = RunInfo {
+            tick: self.tick + 1,
+            handles: &self.handles,
+        };
+        self.inner.run(world, wrapped, run_info);
+        self.tick += 1;
+        Resources::set_tick(&mut self.borrows, self.tick + 1);
+    }
+
+    /// Returns the tick of the latest run of the executor: runs are numbered starting from 1,
+    /// and a tick of 0 means the executor hasn't been ran yet.
+    ///
+    /// Ticks are per executor, and can be compared with change ticks of its resources;
+    /// see [`::changed_since()`](#method.changed_since).
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// Returns names of the systems, in the order they were inserted: the `Debug`
+    /// representation of the handle for systems that have one, the type name
+    /// of the closure otherwise. Names are also used in borrow panic messages.
+    ///
+    /// # Example
+    /// ```rust
+    /// let executor = yaks::Executor::<()>::builder()
+    ///     .system_with_handle(|_context, _resources: (), _queries: ()| {}, "first")
+    ///     .system(|_context, _resources: (), _queries: ()| {})
+    ///     .build();
+    /// let names: Vec<&str> = executor.system_names().collect();
+    /// assert_eq!(names[0], "\"first\"");
+    /// assert!(names[1].contains("{{closure}}"));
+    /// ```
+    pub fn system_names(&self) -> impl ExactSizeIterator<Item = &str> + '_ {
+        self.names.iter().map(String::as_str)
+    }
+
+    /// Returns the tick of the latest run in which the resource was changed,
+    /// or 0 if it never was.
+    ///
+    /// A resource is considered changed if a system borrowed it (or any of its fields)
+    /// mutably, even if nothing was written to it, or if an accumulator or a double-buffered resource parameter
+    /// modified it at the end of the run. Changes made outside of the executor, e.g.
+    /// via [`::resource_mut()`](#method.resource_mut), are not tracked.
+    ///
+    /// The second generic parameter is used to locate the resource in the executor's
+    /// `Resources` tuple, and should be left for the compiler to infer:
+    /// `executor.change_tick::<SomeResource, _>()`.
+    pub fn change_tick<R0, M0>(&self) -> u64
+    where
+        Resources: Get<R0, M0>,
+    {
+        Resources::change_tick(&self.borrows, Resources::index())
+    }
+
+    /// Returns `true` if the resource was changed in any run of the executor after
+    /// the given tick; see [`::change_tick()`](#method.change_tick).
+    ///
+    /// # Example
+    /// ```rust
+    /// # use yaks::Executor;
+    /// # let world = hecs::World::new();
+    /// let mut executor = Executor::<(f32, u32)>::builder()
+    ///     .system(|_context, (some_f32, some_u32): (&mut f32, &u32), _queries: ()| {
+    ///         *some_f32 += *some_u32 as f32;
+    ///     })
+    ///     .build();
+    /// let mut some_f32 = 0f32;
+    /// let mut some_u32 = 1u32;
+    /// let tick = executor.tick();
+    /// executor.run(&world, (&mut some_f32, &mut some_u32));
+    /// assert!(executor.changed_since::<f32, _>(tick));
+    /// assert!(!executor.changed_since::<u32, _>(tick));
+    /// ```
+    pub fn changed_since<R0, M0>(&self, tick: u64) -> bool
+    where
+        Resources: Get<R0, M0>,
+    {
+        self.change_tick::<R0, M0>() > tick
+    }
+
+    /// Returns a reference to a resource owned by the executor.
+    ///
+    /// The second generic parameter is used to locate the resource in the executor's
+    /// `Resources` tuple, and should be left for the compiler to infer:
+    /// `executor.resource::<SomeResource, _>()`.
+    ///
+    /// # Panics
+    /// This function will panic if the executor does not own its resources; see
+    /// [`ExecutorBuilder::build_with_resources()`][bwr].
+    ///
+    /// [bwr]: struct.ExecutorBuilder.html#method.build_with_resources
+    pub fn resource<R0, M0>(&self) -> &R0
+    where
+        Resources: Get<R0, M0>,
+    {
+        self.resources.as_ref().expect(NOT_OWNED).get()
+    }
+
+    /// Returns a mutable reference to a resource owned by the executor;
+    /// see [`::resource()`](#method.resource).
+    ///
+    /// # Panics
+    /// This function will panic if the executor does not own its resources; see
+    /// [`ExecutorBuilder::build_with_resources()`][bwr].
+    ///
+    /// [bwr]: struct.ExecutorBuilder.html#method.build_with_resources
+    pub fn resource_mut<R0, M0>(&mut self) -> &mut R0
     where
-        Resources: RefExtractor<RefSource>,
+        Resources: Get<R0, M0>,
     {
-        Resources::extract_and_run(self, world, resources);
+        self.resources.as_mut().expect(NOT_OWNED).get_mut()
     }
 }
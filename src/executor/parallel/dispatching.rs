@@ -1,10 +1,11 @@
+use fixedbitset::FixedBitSet;
 use hecs::World;
 use parking_lot::Mutex;
 use rayon::prelude::*;
 use std::{collections::HashMap, sync::Arc};
 
-use super::SystemClosure;
-use crate::{ResourceTuple, SystemContext, SystemId};
+use super::{RunInfo, SystemClosure};
+use crate::{ResourceTuple, SystemId};
 
 /// Parallel executor variant, used when all systems are proven to be statically disjoint,
 /// and have no dependencies.
@@ -12,36 +13,66 @@ pub struct Dispatcher<'closures, Resources>
 where
     Resources: ResourceTuple,
 {
-    pub systems: HashMap<SystemId, Arc<Mutex<SystemClosure<'closures, Resources::Wrapped>>>>,
+    /// System closures, and the resources each of them borrows immutably
+    /// together with another system.
+    pub systems: HashMap<SystemId, DispatchedSystem<'closures, Resources>>,
+    /// Resources borrowed immutably by more than one system.
+    pub shared: FixedBitSet,
+    /// Shared resources that live behind a lock excluding shared borrows in the current run.
+    pub exclusive: FixedBitSet,
 }
 
+type DispatchedSystem<'closures, Resources> = (
+    Arc<Mutex<SystemClosure<'closures, <Resources as ResourceTuple>::Wrapped>>>,
+    FixedBitSet,
+);
+
 impl<'closures, Resources> Dispatcher<'closures, Resources>
 where
     Resources: ResourceTuple,
 {
-    pub fn run(&mut self, world: &World, wrapped: Resources::Wrapped) {
-        // All systems are statically disjoint, so they can all be running together at all times.
-        self.systems.par_iter().for_each(|(id, system)| {
+    pub fn run(&mut self, world: &World, wrapped: Resources::Wrapped, run_info: RunInfo) {
+        self.exclusive.clear();
+        for index in self.shared.ones() {
+            self.exclusive
+                .set(index, Resources::wrapped_is_exclusive(&wrapped, index));
+        }
+        let exclusive = &self.exclusive;
+        let run = &|(id, (system, _)): (&SystemId, &DispatchedSystem<Resources>)| {
             let system = &mut *system
                 .try_lock() // TODO should this be .lock() instead?
                 .expect("systems should only be ran once per execution");
-            system(
-                SystemContext {
-                    system_id: Some(*id),
-                    world,
+            system(run_info.context(*id, world), &wrapped);
+        };
+        if exclusive.count_ones(..) == 0 {
+            // All systems are statically disjoint, so they can all be running together
+            // at all times.
+            self.systems.par_iter().for_each(run);
+        } else {
+            // Systems sharing a resource behind an exclusive lock can't run concurrently;
+            // these are ran one by one, alongside all others.
+            let serial = |(_, (_, shared)): &(&SystemId, &DispatchedSystem<Resources>)| {
+                !shared.is_disjoint(exclusive)
+            };
+            rayon::join(
+                || self.systems.iter().filter(serial).for_each(run),
+                || {
+                    self.systems
+                        .par_iter()
+                        .filter(|system| !serial(system))
+                        .for_each(run)
                 },
-                &wrapped,
             );
-        });
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::super::ExecutorParallel;
+    use super::super::{ExecutorParallel, RunInfo};
     use crate::{
         resource::{AtomicBorrow, ResourceWrap},
-        Executor, QueryMarker,
+        Accumulate, Accumulator, Back, DoubleBuffered, Executor, FieldMut, Front, QueryMarker,
     };
     use hecs::World;
 
@@ -49,6 +80,22 @@ mod tests {
     struct B(usize);
     struct C(usize);
 
+    struct D {
+        a: A,
+        b: B,
+    }
+
+    crate::resource_fields!(D { a: A, b: B });
+
+    #[derive(Default)]
+    struct E(usize);
+
+    impl Accumulate for E {
+        fn combine(&mut self, partial: Self) {
+            self.0 += partial.0;
+        }
+    }
+
     #[test]
     fn trivial() {
         ExecutorParallel::<()>::build(
@@ -91,11 +138,71 @@ mod tests {
             AtomicBorrow::new(),
         );
         let wrapped = (&mut a, &mut b, &mut c).wrap(&mut borrows);
-        executor.run(&world, wrapped);
+        executor.run(&world, wrapped, RunInfo::default());
         assert_eq!(a.0, 2);
         assert_eq!(b.0, 3);
     }
 
+    #[test]
+    fn resource_fields_disjoint() {
+        let world = World::new();
+        let mut d = D { a: A(0), b: B(1) };
+        let mut executor = ExecutorParallel::<(D,)>::build(
+            Executor::builder()
+                .system(|_, mut a: FieldMut<D, A>, _: ()| {
+                    a.0 += 1;
+                })
+                .system(|_, mut b: FieldMut<D, B>, _: ()| {
+                    b.0 += 1;
+                }),
+        )
+        .unwrap_to_dispatcher();
+        let mut d = &mut d;
+        let mut borrows = (AtomicBorrow::new(),);
+        let wrapped = d.wrap(&mut borrows);
+        executor.run(&world, wrapped, RunInfo::default());
+        assert_eq!(d.a.0, 1);
+        assert_eq!(d.b.0, 2);
+    }
+
+    #[test]
+    fn accumulators_disjoint() {
+        let world = World::new();
+        let mut e = E(0);
+        let mut executor = ExecutorParallel::<(E,)>::build(
+            Executor::builder()
+                .system(|_, e: Accumulator<E>, _: ()| e.with(|e| e.0 += 1))
+                .system(|_, e: Accumulator<E>, _: ()| e.with(|e| e.0 += 2))
+                .system(|_, e: &mut E, _: ()| e.0 += 4),
+        )
+        .unwrap_to_dispatcher();
+        let mut e_ref = &mut e;
+        let mut borrows = (AtomicBorrow::new(),);
+        let wrapped = e_ref.wrap(&mut borrows);
+        executor.run(&world, wrapped, RunInfo::default());
+        assert_eq!(e.0, 7);
+    }
+
+    #[test]
+    fn double_buffers_disjoint() {
+        let world = World::new();
+        let mut buffers = DoubleBuffered::from_buffers(A(1), A(0));
+        let mut c = C(0);
+        let mut executor = ExecutorParallel::<(DoubleBuffered<A>, C)>::build(
+            Executor::builder()
+                .system(|_, (front, c): (Front<A>, &mut C), _: ()| c.0 += front.0)
+                .system(|_, mut back: Back<A>, _: ()| back.0 += 2),
+        )
+        .unwrap_to_dispatcher();
+        let mut borrows = (AtomicBorrow::new(), AtomicBorrow::new());
+        let wrapped = (&mut buffers, &mut c).wrap(&mut borrows);
+        executor.run(&world, wrapped, RunInfo::default());
+        assert_eq!(c.0, 1);
+        // Buffers are swapped by `Executor`, not by it's variants.
+        assert_eq!(buffers.front().0, 1);
+        assert_eq!(buffers.back().0, 2);
+    }
+
     #[test]
     fn components_disjoint() {
         let mut world = World::new();
@@ -117,7 +224,7 @@ mod tests {
         .unwrap_to_dispatcher();
         let mut borrow = (AtomicBorrow::new(),);
         let wrapped = (&mut a).wrap(&mut borrow);
-        executor.run(&world, wrapped);
+        executor.run(&world, wrapped, RunInfo::default());
         for (_, (b, c)) in world.query::<(&B, &C)>().iter() {
             assert_eq!(b.0, 1);
             assert_eq!(c.0, 1);
@@ -1,3 +1,4 @@
+use fixedbitset::FixedBitSet;
 use hecs::World;
 use parking_lot::Mutex;
 use std::{
@@ -5,8 +6,8 @@ use std::{
     sync::Arc,
 };
 
-use super::SystemClosure;
-use crate::{ArchetypeSet, BorrowSet, ExecutorBuilder, ResourceTuple, SystemId};
+use super::{ArchetypeWriter, RunInfo, SystemClosure};
+use crate::{ArchetypeSet, BorrowSet, ExecutorBuilder, FieldKey, ResourceTuple, SystemId};
 
 mod dispatching;
 mod scheduling;
@@ -26,7 +27,7 @@ where
     pub resource_set: BorrowSet,
     pub component_set: BorrowSet,
     pub archetype_set: ArchetypeSet,
-    pub archetype_writer: Box<dyn Fn(&World, &mut ArchetypeSet) + Send>,
+    pub archetype_writer: Box<ArchetypeWriter>,
     pub dependants: Vec<SystemId>,
     pub dependencies: usize,
     pub unsatisfied_dependencies: usize,
@@ -62,9 +63,24 @@ where
         } = builder;
         // This guarantees iteration order; TODO probably not necessary?..
         let all_component_types = all_component_types.drain().collect::<Vec<_>>();
+        // Resource fields borrowed by any system get their own bits in resource sets.
+        let all_fields = systems
+            .values()
+            .flat_map(|system| {
+                let set = &system.resource_field_set;
+                set.immutable.iter().chain(set.mutable.iter()).copied()
+            })
+            .collect::<HashSet<FieldKey>>()
+            .drain()
+            .collect::<Vec<_>>();
         let mut systems: HashMap<SystemId, System<'closures, Resources>> = systems
             .drain()
-            .map(|(id, system)| {
+            .map(|(id, mut system)| {
+                system.resource_field_set.expand_into(
+                    &mut system.resource_set,
+                    Resources::LENGTH,
+                    &all_fields,
+                );
                 let dependencies = system.dependencies.len();
                 // Remember systems with no dependencies, these will be queued first on run.
                 if dependencies == 0 {
@@ -103,11 +119,28 @@ where
                 }
             }
             if all_disjoint {
+                // Resources several systems borrow immutably; if any of these live behind
+                // an exclusive lock, systems borrowing them can't run concurrently.
+                let mut borrowed = FixedBitSet::with_capacity(Resources::LENGTH);
+                let mut shared = FixedBitSet::with_capacity(Resources::LENGTH);
+                for system in systems.values() {
+                    for index in system.resource_set.immutable.ones() {
+                        if index < Resources::LENGTH && borrowed.put(index) {
+                            shared.insert(index);
+                        }
+                    }
+                }
                 return ExecutorParallel::Dispatching(Dispatcher {
                     systems: systems
                         .drain()
-                        .map(|(id, system)| (id, system.closure))
+                        .map(|(id, system)| {
+                            let mut reads = system.resource_set.immutable;
+                            reads.intersect_with(&shared);
+                            (id, (system.closure, reads))
+                        })
                         .collect(),
+                    shared,
+                    exclusive: FixedBitSet::with_capacity(Resources::LENGTH),
                 });
             }
         }
@@ -144,6 +177,7 @@ where
             systems_running: HashSet::new(),
             systems_just_finished: Vec::new(),
             systems_to_decrement_dependencies: Vec::new(),
+            exclusive: FixedBitSet::with_capacity(Resources::LENGTH),
             sender,
             receiver,
         })
@@ -156,10 +190,10 @@ where
         }
     }
 
-    pub fn run(&mut self, world: &World, wrapped: Resources::Wrapped) {
+    pub fn run(&mut self, world: &World, wrapped: Resources::Wrapped, run_info: RunInfo) {
         match self {
-            ExecutorParallel::Dispatching(dispatcher) => dispatcher.run(world, wrapped),
-            ExecutorParallel::Scheduling(scheduler) => scheduler.run(world, wrapped),
+            ExecutorParallel::Dispatching(dispatcher) => dispatcher.run(world, wrapped, run_info),
+            ExecutorParallel::Scheduling(scheduler) => scheduler.run(world, wrapped, run_info),
         }
     }
 
@@ -1,10 +1,11 @@
 use crossbeam_channel::{Receiver, Sender};
+use fixedbitset::FixedBitSet;
 use hecs::{ArchetypesGeneration, World};
 use rayon::ScopeFifo;
 use std::collections::{HashMap, HashSet};
 
-use super::{System, DISCONNECTED, INVALID_ID};
-use crate::{ResourceTuple, SystemContext, SystemId};
+use super::{RunInfo, System, DISCONNECTED, INVALID_ID};
+use crate::{ResourceTuple, SystemId};
 
 /// Typed `usize` used to cache the amount of dependants the system associated
 /// with a `SystemId` has; avoids hashmap lookups while sorting.
@@ -24,6 +25,9 @@ where
     pub systems_running: HashSet<SystemId>,
     pub systems_just_finished: Vec<SystemId>,
     pub systems_to_decrement_dependencies: Vec<SystemId>,
+    /// Resources that live behind a lock excluding shared borrows in the current run;
+    /// systems borrowing them never run concurrently, see `LockOnDemand`.
+    pub exclusive: FixedBitSet,
     pub sender: Sender<SystemId>,
     pub receiver: Receiver<SystemId>,
 }
@@ -32,12 +36,17 @@ impl<'closures, Resources> Scheduler<'closures, Resources>
 where
     Resources: ResourceTuple,
 {
-    pub fn run(&mut self, world: &World, wrapped: Resources::Wrapped) {
+    pub fn run(&mut self, world: &World, wrapped: Resources::Wrapped, run_info: RunInfo) {
+        self.exclusive.clear();
+        for index in 0..Resources::LENGTH {
+            self.exclusive
+                .set(index, Resources::wrapped_is_exclusive(&wrapped, index));
+        }
         rayon::scope_fifo(|scope| {
             self.prepare(world);
             // All systems have been ran if there are no queued or currently running systems.
             while !(self.systems_to_run_now.is_empty() && self.systems_running.is_empty()) {
-                self.start_all_currently_runnable(scope, world, &wrapped);
+                self.start_all_currently_runnable(scope, world, &wrapped, run_info);
                 self.wait_for_and_process_finished();
             }
         });
@@ -78,11 +87,15 @@ where
         scope: &ScopeFifo<'run>,
         world: &'run World,
         wrapped: &'run Resources::Wrapped,
+        run_info: RunInfo<'run>,
     ) where
         'closures: 'run,
         Resources::BorrowTuple: Send,
         Resources::Wrapped: Send + Sync,
     {
+        // With a single thread in the pool, spawned systems would never start while
+        // this thread is blocked waiting for them to finish; run them in place instead.
+        let run_in_place = rayon::current_num_threads() == 1;
         for (id, _) in &self.systems_to_run_now {
             // Check if a queued system can run concurrently with
             // other systems already running.
@@ -93,20 +106,19 @@ where
                 let system = self.systems.get_mut(id).expect(INVALID_ID).closure.clone();
                 let sender = self.sender.clone();
                 let id = *id;
-                scope.spawn_fifo(move |_| {
+                let task = move || {
                     let system = &mut *system
                         .try_lock() // TODO should this be .lock() instead?
                         .expect("systems should only be ran once per execution");
-                    system(
-                        SystemContext {
-                            system_id: Some(id),
-                            world,
-                        },
-                        wrapped,
-                    );
+                    system(run_info.context(id, world), wrapped);
                     // Notify dispatching thread than this system has finished running.
                     sender.send(id).expect(DISCONNECTED);
-                });
+                };
+                if run_in_place {
+                    task();
+                } else {
+                    scope.spawn_fifo(move |_| task());
+                }
             }
         }
         {
@@ -131,7 +143,7 @@ where
             // A system can't run if the resources it needs are already borrowed incompatibly.
             if !system
                 .resource_set
-                .is_compatible(&running_system.resource_set)
+                .is_compatible_exclusive(&running_system.resource_set, &self.exclusive)
             {
                 return false;
             }
@@ -192,18 +204,26 @@ where
 
 #[cfg(test)]
 mod tests {
-    use super::super::ExecutorParallel;
+    use super::super::{ExecutorParallel, RunInfo};
     use crate::{
         resource::{AtomicBorrow, ResourceWrap},
-        Executor, QueryMarker, SystemContext,
+        Executor, Field, FieldMut, QueryMarker, SystemContext,
     };
     use hecs::World;
     use rayon::{ScopeFifo, ThreadPoolBuilder};
 
     struct A(usize);
     struct B(usize);
+    #[allow(dead_code)]
     struct C(usize);
 
+    struct D {
+        a: A,
+        b: B,
+    }
+
+    crate::resource_fields!(D { a: A, b: B });
+
     fn dummy_system(_: SystemContext, _: (), _: ()) {}
 
     fn local_pool_scope_fifo<'scope, F>(closure: F)
@@ -229,12 +249,12 @@ mod tests {
         let wrapped = ();
         local_pool_scope_fifo(|scope| {
             executor.prepare(&world);
-            executor.start_all_currently_runnable(scope, &world, &wrapped);
+            executor.start_all_currently_runnable(scope, &world, &wrapped, RunInfo::default());
             assert_eq!(executor.systems_running.len(), 1);
             executor.wait_for_and_process_finished();
             assert!(executor.systems_running.is_empty());
 
-            executor.start_all_currently_runnable(scope, &world, &wrapped);
+            executor.start_all_currently_runnable(scope, &world, &wrapped, RunInfo::default());
             assert_eq!(executor.systems_running.len(), 1);
             executor.wait_for_and_process_finished();
             assert!(executor.systems_running.is_empty());
@@ -256,14 +276,14 @@ mod tests {
         let wrapped = ();
         local_pool_scope_fifo(|scope| {
             executor.prepare(&world);
-            executor.start_all_currently_runnable(scope, &world, &wrapped);
+            executor.start_all_currently_runnable(scope, &world, &wrapped, RunInfo::default());
             assert_eq!(executor.systems_running.len(), 3);
             executor.wait_for_one_finished();
             executor.wait_for_one_finished();
             executor.wait_for_and_process_finished();
             assert!(executor.systems_running.is_empty());
 
-            executor.start_all_currently_runnable(scope, &world, &wrapped);
+            executor.start_all_currently_runnable(scope, &world, &wrapped, RunInfo::default());
             assert_eq!(executor.systems_running.len(), 1);
             executor.wait_for_and_process_finished();
             assert!(executor.systems_running.is_empty());
@@ -285,22 +305,22 @@ mod tests {
         let wrapped = ();
         local_pool_scope_fifo(|scope| {
             executor.prepare(&world);
-            executor.start_all_currently_runnable(scope, &world, &wrapped);
+            executor.start_all_currently_runnable(scope, &world, &wrapped, RunInfo::default());
             assert_eq!(executor.systems_running.len(), 1);
             executor.wait_for_and_process_finished();
             assert!(executor.systems_running.is_empty());
 
-            executor.start_all_currently_runnable(scope, &world, &wrapped);
+            executor.start_all_currently_runnable(scope, &world, &wrapped, RunInfo::default());
             assert_eq!(executor.systems_running.len(), 1);
             executor.wait_for_and_process_finished();
             assert!(executor.systems_running.is_empty());
 
-            executor.start_all_currently_runnable(scope, &world, &wrapped);
+            executor.start_all_currently_runnable(scope, &world, &wrapped, RunInfo::default());
             assert_eq!(executor.systems_running.len(), 1);
             executor.wait_for_and_process_finished();
             assert!(executor.systems_running.is_empty());
 
-            executor.start_all_currently_runnable(scope, &world, &wrapped);
+            executor.start_all_currently_runnable(scope, &world, &wrapped, RunInfo::default());
             assert_eq!(executor.systems_running.len(), 1);
             executor.wait_for_and_process_finished();
             assert!(executor.systems_running.is_empty());
@@ -322,22 +342,22 @@ mod tests {
         let wrapped = ();
         local_pool_scope_fifo(|scope| {
             executor.prepare(&world);
-            executor.start_all_currently_runnable(scope, &world, &wrapped);
+            executor.start_all_currently_runnable(scope, &world, &wrapped, RunInfo::default());
             assert_eq!(executor.systems_running.len(), 1);
             executor.wait_for_and_process_finished();
             assert!(executor.systems_running.is_empty());
 
-            executor.start_all_currently_runnable(scope, &world, &wrapped);
+            executor.start_all_currently_runnable(scope, &world, &wrapped, RunInfo::default());
             assert_eq!(executor.systems_running.len(), 1);
             executor.wait_for_and_process_finished();
             assert!(executor.systems_running.is_empty());
 
-            executor.start_all_currently_runnable(scope, &world, &wrapped);
+            executor.start_all_currently_runnable(scope, &world, &wrapped, RunInfo::default());
             assert_eq!(executor.systems_running.len(), 1);
             executor.wait_for_and_process_finished();
             assert!(executor.systems_running.is_empty());
 
-            executor.start_all_currently_runnable(scope, &world, &wrapped);
+            executor.start_all_currently_runnable(scope, &world, &wrapped, RunInfo::default());
             assert_eq!(executor.systems_running.len(), 1);
             executor.wait_for_and_process_finished();
             assert!(executor.systems_running.is_empty());
@@ -360,12 +380,12 @@ mod tests {
         let wrapped = a.wrap(&mut borrows);
         local_pool_scope_fifo(|scope| {
             executor.prepare(&world);
-            executor.start_all_currently_runnable(scope, &world, &wrapped);
+            executor.start_all_currently_runnable(scope, &world, &wrapped, RunInfo::default());
             assert_eq!(executor.systems_running.len(), 1);
             executor.wait_for_and_process_finished();
             assert!(executor.systems_running.is_empty());
 
-            executor.start_all_currently_runnable(scope, &world, &wrapped);
+            executor.start_all_currently_runnable(scope, &world, &wrapped, RunInfo::default());
             assert_eq!(executor.systems_running.len(), 1);
             executor.wait_for_and_process_finished();
             assert!(executor.systems_running.is_empty());
@@ -374,6 +394,42 @@ mod tests {
         assert_eq!(a.0, 1);
     }
 
+    #[test]
+    fn resource_fields_incompatible() {
+        let world = World::new();
+        let mut executor = ExecutorParallel::<(D,)>::build(
+            Executor::builder()
+                .system(|_, (_, mut b): (Field<D, A>, FieldMut<D, B>), _: ()| b.0 += 1)
+                .system(|_, mut a: FieldMut<D, A>, _: ()| a.0 += 1)
+                .system(|_, _: &D, _: ()| {}),
+        )
+        .unwrap_to_scheduler();
+        let mut d = D { a: A(0), b: B(1) };
+        let mut d = &mut d;
+        let mut borrows = (AtomicBorrow::new(),);
+        let wrapped = d.wrap(&mut borrows);
+        local_pool_scope_fifo(|scope| {
+            executor.prepare(&world);
+            executor.start_all_currently_runnable(scope, &world, &wrapped, RunInfo::default());
+            assert_eq!(executor.systems_running.len(), 1);
+            executor.wait_for_and_process_finished();
+            assert!(executor.systems_running.is_empty());
+
+            executor.start_all_currently_runnable(scope, &world, &wrapped, RunInfo::default());
+            assert_eq!(executor.systems_running.len(), 1);
+            executor.wait_for_and_process_finished();
+            assert!(executor.systems_running.is_empty());
+
+            executor.start_all_currently_runnable(scope, &world, &wrapped, RunInfo::default());
+            assert_eq!(executor.systems_running.len(), 1);
+            executor.wait_for_and_process_finished();
+            assert!(executor.systems_running.is_empty());
+            assert!(executor.systems_to_run_now.is_empty());
+        });
+        assert_eq!(d.a.0, 1);
+        assert_eq!(d.b.0, 2);
+    }
+
     #[test]
     fn resources_incompatible_mutable_mutable() {
         let world = World::new();
@@ -389,12 +445,12 @@ mod tests {
         let wrapped = a.wrap(&mut borrows);
         local_pool_scope_fifo(|scope| {
             executor.prepare(&world);
-            executor.start_all_currently_runnable(scope, &world, &wrapped);
+            executor.start_all_currently_runnable(scope, &world, &wrapped, RunInfo::default());
             assert_eq!(executor.systems_running.len(), 1);
             executor.wait_for_and_process_finished();
             assert!(executor.systems_running.is_empty());
 
-            executor.start_all_currently_runnable(scope, &world, &wrapped);
+            executor.start_all_currently_runnable(scope, &world, &wrapped, RunInfo::default());
             assert_eq!(executor.systems_running.len(), 1);
             executor.wait_for_and_process_finished();
             assert!(executor.systems_running.is_empty());
@@ -423,12 +479,12 @@ mod tests {
         let wrapped = a.wrap(&mut borrows);
         local_pool_scope_fifo(|scope| {
             executor.prepare(&world);
-            executor.start_all_currently_runnable(scope, &world, &wrapped);
+            executor.start_all_currently_runnable(scope, &world, &wrapped, RunInfo::default());
             assert_eq!(executor.systems_running.len(), 1);
             executor.wait_for_and_process_finished();
             assert!(executor.systems_running.is_empty());
 
-            executor.start_all_currently_runnable(scope, &world, &wrapped);
+            executor.start_all_currently_runnable(scope, &world, &wrapped, RunInfo::default());
             assert_eq!(executor.systems_running.len(), 1);
             executor.wait_for_and_process_finished();
             assert!(executor.systems_running.is_empty());
@@ -463,12 +519,12 @@ mod tests {
         let wrapped = a.wrap(&mut borrows);
         local_pool_scope_fifo(|scope| {
             executor.prepare(&world);
-            executor.start_all_currently_runnable(scope, &world, &wrapped);
+            executor.start_all_currently_runnable(scope, &world, &wrapped, RunInfo::default());
             assert_eq!(executor.systems_running.len(), 1);
             executor.wait_for_and_process_finished();
             assert!(executor.systems_running.is_empty());
 
-            executor.start_all_currently_runnable(scope, &world, &wrapped);
+            executor.start_all_currently_runnable(scope, &world, &wrapped, RunInfo::default());
             assert_eq!(executor.systems_running.len(), 1);
             executor.wait_for_and_process_finished();
             assert!(executor.systems_running.is_empty());
@@ -504,7 +560,7 @@ mod tests {
         let wrapped = a.wrap(&mut borrows);
         local_pool_scope_fifo(|scope| {
             executor.prepare(&world);
-            executor.start_all_currently_runnable(scope, &world, &wrapped);
+            executor.start_all_currently_runnable(scope, &world, &wrapped, RunInfo::default());
             assert_eq!(executor.systems_running.len(), 2);
             executor.wait_for_one_finished();
             executor.wait_for_and_process_finished();
@@ -524,12 +580,12 @@ mod tests {
         let wrapped = a.wrap(&mut borrows);
         local_pool_scope_fifo(|scope| {
             executor.prepare(&world);
-            executor.start_all_currently_runnable(scope, &world, &wrapped);
+            executor.start_all_currently_runnable(scope, &world, &wrapped, RunInfo::default());
             assert_eq!(executor.systems_running.len(), 1);
             executor.wait_for_and_process_finished();
             assert!(executor.systems_running.is_empty());
 
-            executor.start_all_currently_runnable(scope, &world, &wrapped);
+            executor.start_all_currently_runnable(scope, &world, &wrapped, RunInfo::default());
             assert_eq!(executor.systems_running.len(), 1);
             executor.wait_for_and_process_finished();
             assert!(executor.systems_running.is_empty());
@@ -544,7 +600,7 @@ mod tests {
             .for_each(|entity| world.despawn(entity).unwrap());
         rayon::scope(|scope| {
             executor.prepare(&world);
-            executor.start_all_currently_runnable(scope, &world, &wrapped);
+            executor.start_all_currently_runnable(scope, &world, &wrapped, RunInfo::default());
             // TODO this fails. Suggest upstream changes?
             assert_eq!(executor.systems_running.len(), 2);
             executor.wait_for_one_finished();
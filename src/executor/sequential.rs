@@ -1,7 +1,7 @@
 use hecs::World;
 
-use super::SystemClosure;
-use crate::{ExecutorBuilder, ResourceTuple, SystemContext, SystemId};
+use super::{RunInfo, SystemClosure};
+use crate::{ExecutorBuilder, ResourceTuple, SystemId};
 
 pub struct ExecutorSequential<'closures, Resources>
 where
@@ -20,21 +20,15 @@ where
             .drain()
             .map(|(id, system)| (id, system.closure))
             .collect();
-        systems.sort_by(|(a, _), (b, _)| a.cmp(b));
+        systems.sort_by_key(|(id, _)| *id);
         ExecutorSequential { systems }
     }
 
     pub fn force_archetype_recalculation(&mut self) {}
 
-    pub fn run(&mut self, world: &World, wrapped: Resources::Wrapped) {
+    pub fn run(&mut self, world: &World, wrapped: Resources::Wrapped, run_info: RunInfo) {
         for (id, closure) in &mut self.systems {
-            closure(
-                SystemContext {
-                    system_id: Some(*id),
-                    world,
-                },
-                &wrapped,
-            );
+            closure(run_info.context(*id, world), &wrapped);
         }
     }
 }
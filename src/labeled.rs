@@ -0,0 +1,76 @@
+use std::ops::{Deref, DerefMut};
+
+/// A resource of type `R` distinguished by a numeric `LABEL`, allowing several instances
+/// of the same type to be used as separate resources of an executor.
+///
+/// Each label is a distinct type: systems borrowing differently labeled instances
+/// do not conflict with each other and may run concurrently.
+///
+/// References to a plain `R` can be converted into references to a labeled one with
+/// [`::from_ref()`](#method.from_ref) and [`::from_mut()`](#method.from_mut), so values
+/// don't have to be stored wrapped.
+///
+/// # Example
+/// ```rust
+/// # use yaks::{Executor, Labeled};
+/// # let world = hecs::World::new();
+/// struct Camera(f32);
+///
+/// const MAIN: usize = 0;
+/// const MINIMAP: usize = 1;
+///
+/// let mut executor = Executor::<(Labeled<MAIN, Camera>, Labeled<MINIMAP, Camera>)>::builder()
+///     .system(|_context, main: &mut Labeled<MAIN, Camera>, _queries: ()| {
+///         main.0 += 1.0;
+///     })
+///     .system(|_context, minimap: &mut Labeled<MINIMAP, Camera>, _queries: ()| {
+///         minimap.0 -= 1.0;
+///     })
+///     .build();
+/// let mut main = Camera(0.0);
+/// let mut minimap = Camera(0.0);
+/// executor.run(&world, (Labeled::from_mut(&mut main), Labeled::from_mut(&mut minimap)));
+/// assert_eq!(main.0, 1.0);
+/// assert_eq!(minimap.0, -1.0);
+/// ```
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Labeled<const LABEL: usize, R>(R);
+
+impl<const LABEL: usize, R> Labeled<LABEL, R> {
+    /// Wraps a value.
+    pub fn new(resource: R) -> Self {
+        Self(resource)
+    }
+
+    /// Unwraps the value.
+    pub fn into_inner(self) -> R {
+        self.0
+    }
+
+    /// Converts a reference to a value into a reference to a labeled value.
+    pub fn from_ref(resource: &R) -> &Self {
+        // Safe due to `#[repr(transparent)]`.
+        unsafe { &*(resource as *const R as *const Self) }
+    }
+
+    /// Converts a mutable reference to a value into a mutable reference to a labeled value.
+    pub fn from_mut(resource: &mut R) -> &mut Self {
+        // Safe due to `#[repr(transparent)]`.
+        unsafe { &mut *(resource as *mut R as *mut Self) }
+    }
+}
+
+impl<const LABEL: usize, R> Deref for Labeled<LABEL, R> {
+    type Target = R;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<const LABEL: usize, R> DerefMut for Labeled<LABEL, R> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
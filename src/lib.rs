@@ -30,9 +30,11 @@
 //! # Cargo features
 //!
 //! - `parallel` - enabled by default; can be disabled to force `yaks` to work on a single thread.
-//! Useful for writing the code once, and running it on platforms with or without threading.
+//!   Useful for writing the code once, and running it on platforms with or without threading.
 //! - `resources-interop` - when enabled, allows `Executor::run()` to also
-//! accept `Resources` struct from the [`resources`] crate in place of resources argument.
+//!   accept `Resources` struct from the [`resources`] crate in place of resources argument;
+//!   `Executor::try_run()` and `System::try_run()` return an error instead of panicking
+//!   if a resource is missing or already borrowed.
 //!
 //! [`resources`]: https://crates.io/crates/resources
 //!
@@ -43,7 +45,7 @@
 //!
 //! ```rust
 //! use hecs::{With, Without, World};
-//! use yaks::{Executor, QueryMarker};
+//! use yaks::{BatchSize, Executor, QueryMarker};
 //!
 //! let mut world = World::new();
 //! let mut entities = 0u32;
@@ -95,9 +97,10 @@
 //!             *unsigned += average.round() as u32;
 //!         },
 //!     );
+//!     // Batch size can also be picked for each archetype the query matches.
 //!     yaks::batch(
 //!         &mut context.query(without_f32),
-//!         entities / 8,
+//!         BatchSize::auto(context.archetypes()),
 //!         |_entity, unsigned| {
 //!             *unsigned *= average.round() as u32;
 //!         },
@@ -113,23 +116,44 @@ mod tuple_macro;
 #[cfg(feature = "parallel")]
 mod access_set;
 mod batch;
+mod events;
 mod executor;
+mod labeled;
+mod local;
+mod query_access;
 mod query_bundle;
+mod query_entities;
 mod query_marker;
 mod resource;
 #[cfg(feature = "resources-interop")]
 mod resources_interop;
 mod run;
 mod system_context;
+mod system_param;
 
 #[cfg(feature = "parallel")]
-use access_set::{ArchetypeSet, BorrowSet, BorrowTypeSet, TypeSet};
+use access_set::{ArchetypeSet, BorrowFieldSet, BorrowSet, BorrowTypeSet, FieldKey, TypeSet};
 use executor::SystemId;
 use query_bundle::QueryBundle;
-use resource::{Fetch, RefExtractor, ResourceTuple};
+use resource::{Get, ResourceTuple};
 
-pub use batch::batch;
+pub use batch::{
+    batch, batch_entities, batch_pairs, batch_pairs_with, batch_reduce, par_iter, BatchSize,
+    ParIter,
+};
+pub use events::{EventReader, EventWriter, Events};
 pub use executor::{Executor, ExecutorBuilder};
+pub use labeled::Labeled;
+pub use local::Local;
+pub use query_access::{ComponentAccess, QueryAccess};
+pub use query_entities::{QueryEntities, QueryMany, QueryManyError};
 pub use query_marker::QueryMarker;
+pub use resource::{
+    Accumulate, Accumulator, Back, Changed, DoubleBuffered, Field, FieldMut, Front, LockOnDemand,
+    ResourceField, ResourceProvider,
+};
+#[cfg(feature = "resources-interop")]
+pub use resources_interop::{FetchError, TryRefExtractor};
 pub use run::System;
 pub use system_context::SystemContext;
+pub use system_param::{SystemAccess, SystemParam};
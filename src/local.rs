@@ -0,0 +1,75 @@
+use hecs::World;
+use std::ops::{Deref, DerefMut};
+
+use crate::{SystemAccess, SystemParam};
+
+/// A value owned by the system, persisting between it's runs; used as
+/// a [`SystemParam`](trait.SystemParam.html) in the resources argument of a system.
+///
+/// The value is created with `T::default()` when the system is inserted into
+/// an [`ExecutorBuilder`](struct.ExecutorBuilder.html), is not shared with any other system,
+/// and does not need to be part of the executor's resources.
+///
+/// # Example
+/// ```rust
+/// # use yaks::{Executor, Local, SystemContext};
+/// # let world = hecs::World::new();
+/// fn count_runs(_context: SystemContext, mut runs: Local<u32>, _queries: ()) {
+///     *runs += 1;
+/// }
+///
+/// let mut executor = Executor::<()>::builder()
+///     .system(count_runs)
+///     .system(count_runs)
+///     .build();
+/// executor.run(&world, ());
+/// executor.run(&world, ());
+///
+/// // Outside of an executor the value has to be provided by the caller.
+/// use yaks::System;
+/// let mut runs = 0;
+/// count_runs.run(&world, Local::new(&mut runs));
+/// assert_eq!(runs, 1);
+/// ```
+pub struct Local<'a, T>(&'a mut T);
+
+impl<'a, T> Local<'a, T> {
+    /// Wraps a mutable reference, for calling systems as plain functions;
+    /// see [`System`](trait.System.html).
+    pub fn new(value: &'a mut T) -> Self {
+        Self(value)
+    }
+}
+
+impl<T> Deref for Local<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.0
+    }
+}
+
+impl<T> DerefMut for Local<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.0
+    }
+}
+
+impl<'a, Cells, T> SystemParam<'a, Cells, ()> for Local<'a, T>
+where
+    T: Default + Send + Sync + 'static,
+{
+    type State = T;
+
+    fn init_state() -> Self::State {
+        T::default()
+    }
+
+    fn declare_access(_: &mut SystemAccess) {}
+
+    fn fetch(state: &'a mut Self::State, _: &'a Cells, _: &'a World) -> Self {
+        Local(state)
+    }
+
+    unsafe fn release(_: &'a Cells) {}
+}
@@ -0,0 +1,157 @@
+use hecs::{Component, Query, With, Without};
+use std::marker::PhantomData;
+
+#[cfg(feature = "parallel")]
+use std::any::TypeId;
+
+#[cfg(feature = "parallel")]
+use crate::BorrowTypeSet;
+
+/// Collects the component types a query may borrow; see [`QueryAccess`](trait.QueryAccess.html).
+///
+/// It cannot be instantiated directly, an executor creates one for each query
+/// while building.
+pub struct ComponentAccess<'a> {
+    #[cfg(feature = "parallel")]
+    component_type_set: &'a mut BorrowTypeSet,
+    phantom_data: PhantomData<&'a mut ()>,
+}
+
+impl<'a> ComponentAccess<'a> {
+    #[cfg(feature = "parallel")]
+    pub(crate) fn new(component_type_set: &'a mut BorrowTypeSet) -> Self {
+        Self {
+            component_type_set,
+            phantom_data: PhantomData,
+        }
+    }
+
+    /// Declares that the query may read components of type `C0`.
+    pub fn read<C0>(&mut self)
+    where
+        C0: Component,
+    {
+        #[cfg(feature = "parallel")]
+        self.component_type_set.immutable.insert(TypeId::of::<C0>());
+    }
+
+    /// Declares that the query may write components of type `C0`.
+    pub fn write<C0>(&mut self)
+    where
+        C0: Component,
+    {
+        #[cfg(feature = "parallel")]
+        self.component_type_set.mutable.insert(TypeId::of::<C0>());
+    }
+}
+
+/// Specifies which components a [`hecs::Query`](../hecs/trait.Query.html) may borrow,
+/// allowing it to be used in a [`QueryMarker`](struct.QueryMarker.html).
+///
+/// Implemented for all queries provided by `hecs`. Custom queries must declare every
+/// component type they may borrow and whether that borrow is unique; executor relies
+/// on this to decide which systems can run concurrently, so an incomplete declaration
+/// will cause a panic when two systems make conflicting borrows.
+///
+/// # Example
+/// ```rust
+/// # use yaks::{ComponentAccess, QueryAccess, QueryMarker, SystemContext};
+/// # struct Pos;
+/// # struct Vel;
+/// // A query that behaves exactly like `(&mut Pos, &Vel)`.
+/// struct Motion;
+///
+/// impl hecs::Query for Motion {
+///     type Fetch = <(&'static mut Pos, &'static Vel) as hecs::Query>::Fetch;
+/// }
+///
+/// impl QueryAccess for Motion {
+///     fn declare_access(access: &mut ComponentAccess) {
+///         access.write::<Pos>();
+///         access.read::<Vel>();
+///     }
+/// }
+///
+/// fn some_system(context: SystemContext, _resources: (), query: QueryMarker<Motion>) {
+///     for (_entity, (_pos, _vel)) in context.query(query).iter() {}
+/// }
+/// ```
+pub trait QueryAccess: Query {
+    /// Declares component types the query may borrow.
+    fn declare_access(access: &mut ComponentAccess);
+}
+
+impl QueryAccess for () {
+    fn declare_access(_: &mut ComponentAccess) {}
+}
+
+impl<C0> QueryAccess for &'_ C0
+where
+    C0: Component,
+{
+    fn declare_access(access: &mut ComponentAccess) {
+        access.read::<C0>();
+    }
+}
+
+impl<C0> QueryAccess for &'_ mut C0
+where
+    C0: Component,
+{
+    fn declare_access(access: &mut ComponentAccess) {
+        access.write::<C0>();
+    }
+}
+
+impl<Q0> QueryAccess for Option<Q0>
+where
+    Q0: QueryAccess,
+{
+    fn declare_access(access: &mut ComponentAccess) {
+        Q0::declare_access(access);
+    }
+}
+
+impl<C0, Q0> QueryAccess for With<C0, Q0>
+where
+    C0: Component,
+    Q0: QueryAccess,
+{
+    fn declare_access(access: &mut ComponentAccess) {
+        Q0::declare_access(access);
+    }
+}
+
+impl<C0, Q0> QueryAccess for Without<C0, Q0>
+where
+    C0: Component,
+    Q0: QueryAccess,
+{
+    fn declare_access(access: &mut ComponentAccess) {
+        Q0::declare_access(access);
+    }
+}
+
+impl<Q0> QueryAccess for (Q0,)
+where
+    Q0: QueryAccess,
+{
+    fn declare_access(access: &mut ComponentAccess) {
+        Q0::declare_access(access);
+    }
+}
+
+macro_rules! impl_query_access {
+    ($($letter:ident),*) => {
+        impl<$($letter),*> QueryAccess for ($($letter,)*)
+        where
+            $($letter: QueryAccess,)*
+        {
+            fn declare_access(access: &mut ComponentAccess) {
+                $($letter::declare_access(access);)*
+            }
+        }
+    }
+}
+
+impl_for_tuples!(impl_query_access);
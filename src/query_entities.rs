@@ -0,0 +1,259 @@
+use hecs::{Access, Archetype, Entity, Fetch, Query, QueryItem, World};
+use std::{
+    any::TypeId,
+    collections::{HashMap, HashSet},
+    error::Error,
+    fmt,
+    marker::PhantomData,
+    ptr::NonNull,
+};
+
+/// Query that borrows nothing, fetching the archetype an entity is in and it's position
+/// there; lets `hecs::World::query_one()` locate entities.
+struct Locate;
+
+impl Query for Locate {
+    type Fetch = FetchLocation;
+}
+
+struct FetchLocation(NonNull<Archetype>);
+
+unsafe impl<'a> Fetch<'a> for FetchLocation {
+    type Item = (NonNull<Archetype>, usize);
+    type State = ();
+
+    fn dangling() -> Self {
+        FetchLocation(NonNull::dangling())
+    }
+
+    fn access(_: &Archetype) -> Option<Access> {
+        Some(Access::Iterate)
+    }
+
+    fn borrow(_: &Archetype, _: Self::State) {}
+
+    fn prepare(_: &Archetype) -> Option<Self::State> {
+        Some(())
+    }
+
+    fn execute(archetype: &'a Archetype, _: Self::State) -> Self {
+        FetchLocation(NonNull::from(archetype))
+    }
+
+    fn release(_: &Archetype, _: Self::State) {}
+
+    fn for_each_borrow(_: impl FnMut(TypeId, bool)) {}
+
+    unsafe fn get(&self, n: usize) -> Self::Item {
+        (self.0, n)
+    }
+}
+
+/// Dynamic borrows of components accessed by a query in archetypes of specific entities,
+/// released when dropped. Unlike a `hecs::QueryOne` per entity, allows fetching items
+/// of several distinct entities at once, even if they share an archetype.
+pub(crate) struct EntityBorrows<'w, Q>
+where
+    Q: Query,
+{
+    entities: Vec<Entity>,
+    archetypes: Vec<&'w Archetype>,
+    // Index into `archetypes` and position within that archetype of each entity,
+    // or `None` if the entity doesn't exist or doesn't match the query.
+    locations: Vec<Option<(usize, usize)>>,
+    phantom_data: PhantomData<Q>,
+}
+
+impl<'w, Q> EntityBorrows<'w, Q>
+where
+    Q: Query,
+{
+    /// Locates the entities and borrows their archetypes, or returns the first entity
+    /// that is listed more than once.
+    ///
+    /// Panics if any of the borrows conflict with existing ones.
+    pub(crate) fn new(world: &'w World, entities: &[Entity]) -> Result<Self, Entity> {
+        let mut unique = HashSet::with_capacity(entities.len());
+        if let Some(duplicate) = entities.iter().find(|entity| !unique.insert(**entity)) {
+            return Err(*duplicate);
+        }
+        let mut borrows = EntityBorrows {
+            entities: entities.to_vec(),
+            archetypes: Vec::new(),
+            locations: Vec::with_capacity(entities.len()),
+            phantom_data: PhantomData,
+        };
+        // Indices into `archetypes`, by address of the archetype.
+        let mut indices: HashMap<NonNull<Archetype>, usize> = HashMap::new();
+        for entity in entities {
+            let location = world
+                .query_one::<Locate>(*entity)
+                .ok()
+                .and_then(|mut query| query.get())
+                .and_then(|(archetype, position)| {
+                    if let Some(index) = indices.get(&archetype) {
+                        return Some((*index, position));
+                    }
+                    // Archetypes live for as long as the world is borrowed.
+                    let archetype_ref: &'w Archetype = unsafe { &*archetype.as_ptr() };
+                    Q::Fetch::prepare(archetype_ref)?;
+                    indices.insert(archetype, borrows.archetypes.len());
+                    borrows.archetypes.push(archetype_ref);
+                    Some((borrows.archetypes.len() - 1, position))
+                });
+            borrows.locations.push(location);
+        }
+        for archetype in &borrows.archetypes {
+            let state = Q::Fetch::prepare(archetype)
+                .expect("archetype should match the query it was found for");
+            Q::Fetch::borrow(archetype, state);
+        }
+        Ok(borrows)
+    }
+
+    /// Returns the entities, in the order they were listed.
+    pub(crate) fn entities(&self) -> &[Entity] {
+        &self.entities
+    }
+
+    /// Returns the first entity that doesn't exist or doesn't match the query.
+    pub(crate) fn first_missing(&self) -> Option<Entity> {
+        self.entities
+            .iter()
+            .zip(&self.locations)
+            .find(|(_, location)| location.is_none())
+            .map(|(entity, _)| *entity)
+    }
+
+    /// Fetches the item of the entity listed at given position; `None` if the entity
+    /// doesn't exist or doesn't match the query.
+    ///
+    /// Safety: items of the same entity must not be alive at the same time.
+    unsafe fn item<'s>(&'s self, position: usize) -> Option<QueryItem<'s, Q>> {
+        self.locations[position].map(|(archetype, index)| {
+            let archetype: &'s Archetype = self.archetypes[archetype];
+            let state = <Q::Fetch as Fetch<'s>>::prepare(archetype)
+                .expect("archetype should match the query it was borrowed for");
+            <Q::Fetch as Fetch<'s>>::execute(archetype, state).get(index)
+        })
+    }
+
+    /// Fetches items of the entities, in the order they were listed; the item is `None`
+    /// if the entity doesn't exist or doesn't match the query.
+    pub(crate) fn items<'s>(&'s mut self) -> Vec<(Entity, Option<QueryItem<'s, Q>>)> {
+        let fetches: Vec<Q::Fetch> = self
+            .archetypes
+            .iter()
+            .map(|archetype| {
+                let archetype: &'s Archetype = archetype;
+                let state = <Q::Fetch as Fetch<'s>>::prepare(archetype)
+                    .expect("archetype should match the query it was borrowed for");
+                <Q::Fetch as Fetch<'s>>::execute(archetype, state)
+            })
+            .collect();
+        self.entities
+            .iter()
+            .zip(&self.locations)
+            .map(|(entity, location)| {
+                // Entities are distinct, and so are their positions; the archetypes are
+                // borrowed for as long as `self` is.
+                let item =
+                    location.map(|(archetype, index)| unsafe { fetches[archetype].get(index) });
+                (*entity, item)
+            })
+            .collect()
+    }
+}
+
+impl<Q> Drop for EntityBorrows<'_, Q>
+where
+    Q: Query,
+{
+    fn drop(&mut self) {
+        for archetype in &self.archetypes {
+            if let Some(state) = Q::Fetch::prepare(archetype) {
+                Q::Fetch::release(archetype, state);
+            }
+        }
+    }
+}
+
+/// A query against a list of distinct entities, prepared by
+/// [`SystemContext::query_entities()`](struct.SystemContext.html#method.query_entities).
+///
+/// Borrows components of the query in archetypes of the entities until dropped. Items are
+/// processed in parallel by [`yaks::batch_entities()`](fn.batch_entities.html).
+pub struct QueryEntities<'w, Q>
+where
+    Q: Query,
+{
+    pub(crate) borrows: EntityBorrows<'w, Q>,
+}
+
+impl<'w, Q> QueryEntities<'w, Q>
+where
+    Q: Query,
+{
+    /// Returns the entities, in the order they were listed.
+    pub fn entities(&self) -> &[Entity] {
+        self.borrows.entities()
+    }
+}
+
+/// Error returned by
+/// [`SystemContext::query_many()`](struct.SystemContext.html#method.query_many).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QueryManyError {
+    /// The entity is listed more than once.
+    Duplicate(Entity),
+    /// The entity does not exist.
+    NoSuchEntity(Entity),
+    /// The entity exists, but does not match the query.
+    Unsatisfied(Entity),
+}
+
+impl fmt::Display for QueryManyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            QueryManyError::Duplicate(entity) => {
+                write!(f, "entity {:?} is listed more than once", entity)
+            }
+            QueryManyError::NoSuchEntity(entity) => write!(f, "no such entity: {:?}", entity),
+            QueryManyError::Unsatisfied(entity) => {
+                write!(f, "entity {:?} does not match the query", entity)
+            }
+        }
+    }
+}
+
+impl Error for QueryManyError {}
+
+/// A query against a fixed number of distinct entities, prepared by
+/// [`SystemContext::query_many()`](struct.SystemContext.html#method.query_many).
+///
+/// Borrows components of the query in archetypes of the entities until dropped.
+pub struct QueryMany<'w, Q, const N: usize>
+where
+    Q: Query,
+{
+    pub(crate) borrows: EntityBorrows<'w, Q>,
+}
+
+impl<'w, Q, const N: usize> QueryMany<'w, Q, N>
+where
+    Q: Query,
+{
+    /// Returns the items of the entities, in the order they were listed.
+    pub fn get(&mut self) -> [QueryItem<'_, Q>; N] {
+        let borrows = &self.borrows;
+        let mut position = 0;
+        [(); N].map(|_| {
+            // Entities are distinct, and `self` is borrowed mutably for as long
+            // as the items are alive.
+            let item = unsafe { borrows.item(position) }
+                .expect("entities should have been checked to match the query");
+            position += 1;
+            item
+        })
+    }
+}
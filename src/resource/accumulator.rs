@@ -0,0 +1,214 @@
+use hecs::World;
+use parking_lot::Mutex;
+
+use super::Contains;
+use crate::{SystemAccess, SystemParam};
+
+/// Specifies how partial values of a resource produced by
+/// [`Accumulator`](struct.Accumulator.html)s are merged together, and into the resource.
+///
+/// # Example
+/// ```rust
+/// # use yaks::Accumulate;
+/// #[derive(Default)]
+/// struct Stats {
+///     collisions: u32,
+///     names: Vec<String>,
+/// }
+///
+/// impl Accumulate for Stats {
+///     fn combine(&mut self, partial: Self) {
+///         self.collisions += partial.collisions;
+///         self.names.extend(partial.names);
+///     }
+/// }
+/// ```
+pub trait Accumulate: Default + Send + Sync + 'static {
+    /// Merges a partial value into this one.
+    fn combine(&mut self, partial: Self);
+}
+
+/// Collects contributions to a resource of type `R`; used as
+/// a [`SystemParam`](trait.SystemParam.html) in the resources argument of a system.
+///
+/// Instead of borrowing the resource, each thread the system (or a
+/// [`yaks::batch()`](fn.batch.html) within it) runs on gets it's own partial value,
+/// starting at `R::default()`. When the system returns, the partial values are merged
+/// together with [`Accumulate::combine()`](trait.Accumulate.html#tymethod.combine),
+/// and, once all systems of the executor have finished, into the resource itself.
+///
+/// Since accumulators never touch the resource while systems run, they don't conflict
+/// with each other or with systems borrowing the resource; the latter will not see
+/// contributions made in the same run.
+///
+/// # Example
+/// ```rust
+/// # use yaks::{Accumulate, Accumulator, Executor};
+/// # let world = hecs::World::new();
+/// #[derive(Default)]
+/// struct Collisions(u32);
+///
+/// impl Accumulate for Collisions {
+///     fn combine(&mut self, partial: Self) {
+///         self.0 += partial.0;
+///     }
+/// }
+///
+/// let mut collisions = Collisions(0);
+/// let mut executor = Executor::<(Collisions,)>::builder()
+///     // These two systems may run concurrently.
+///     .system(|_context, collisions: Accumulator<Collisions>, _queries: ()| {
+///         collisions.with(|collisions| collisions.0 += 1);
+///     })
+///     .system(|_context, collisions: Accumulator<Collisions>, _queries: ()| {
+///         collisions.with(|collisions| collisions.0 += 2);
+///     })
+///     .build();
+/// executor.run(&world, &mut collisions);
+/// assert_eq!(collisions.0, 3);
+///
+/// // Outside of an executor the resource has to be provided by the caller.
+/// use yaks::{System, SystemContext};
+/// fn collide(_context: SystemContext, collisions: Accumulator<Collisions>, _queries: ()) {
+///     collisions.with(|collisions| collisions.0 += 1);
+/// }
+/// collide.run(&world, Accumulator::new(&mut collisions));
+/// assert_eq!(collisions.0, 4);
+/// ```
+pub struct Accumulator<'a, R>
+where
+    R: Accumulate,
+{
+    partials: Partials<'a, R>,
+    resources: *const (),
+    accumulate: fn(*const (), R),
+}
+
+/// Partial values of an accumulator: one per thread in an executor,
+/// or a single one when calling systems as plain functions.
+enum Partials<'a, R> {
+    Borrowed(&'a [Mutex<Option<R>>]),
+    Owned([Mutex<Option<R>>; 1]),
+}
+
+impl<R> Partials<'_, R> {
+    fn as_slice(&self) -> &[Mutex<Option<R>>] {
+        match self {
+            Partials::Borrowed(partials) => partials,
+            Partials::Owned(partials) => partials,
+        }
+    }
+}
+
+// Resources are only ever accessed through a `Contains` implementor, which is `Sync`.
+unsafe impl<R> Send for Accumulator<'_, R> where R: Accumulate {}
+
+unsafe impl<R> Sync for Accumulator<'_, R> where R: Accumulate {}
+
+impl<'a, R> Accumulator<'a, R>
+where
+    R: Accumulate,
+{
+    /// Accumulates into a resource when dropped, for calling systems as plain functions;
+    /// see [`System`](trait.System.html).
+    pub fn new(resource: &'a mut R) -> Self {
+        Self {
+            partials: Partials::Owned([Mutex::new(None)]),
+            resources: resource as *mut R as *const (),
+            accumulate: combine_into::<R>,
+        }
+    }
+
+    /// Calls the closure with the partial value of the current thread.
+    ///
+    /// The closure may call `with()` again, directly or from parallel work it starts
+    /// (e.g. [`yaks::batch()`](fn.batch.html)); contributions made meanwhile are combined
+    /// with the value it was given once it returns.
+    pub fn with<T>(&self, closure: impl FnOnce(&mut R) -> T) -> T {
+        let partials = self.partials.as_slice();
+        let slot = &partials[partial_index(partials.len())];
+        // The slot isn't locked while the closure runs, since work stolen by this thread
+        // in the meantime may contribute to it as well.
+        let mut partial = slot.lock().take().unwrap_or_default();
+        let result = closure(&mut partial);
+        let mut slot = slot.lock();
+        match &mut *slot {
+            Some(other) => other.combine(partial),
+            None => *slot = Some(partial),
+        }
+        result
+    }
+}
+
+impl<R> Drop for Accumulator<'_, R>
+where
+    R: Accumulate,
+{
+    fn drop(&mut self) {
+        for partial in self.partials.as_slice() {
+            if let Some(partial) = partial.lock().take() {
+                (self.accumulate)(self.resources, partial);
+            }
+        }
+    }
+}
+
+/// Returns index of the partial value of the current thread; the last one is shared
+/// by threads outside of the `rayon` thread pool.
+pub(crate) fn partial_index(partials: usize) -> usize {
+    #[cfg(feature = "parallel")]
+    if let Some(index) = rayon::current_thread_index() {
+        if index + 1 < partials {
+            return index;
+        }
+    }
+    partials - 1
+}
+
+fn accumulate_into<T, M0, R0>(resources: *const (), partial: R0)
+where
+    T: Contains<R0, M0>,
+    R0: Accumulate,
+{
+    unsafe { &*(resources as *const T) }.accumulate(partial, R0::combine);
+}
+
+fn combine_into<R0>(resource: *const (), partial: R0)
+where
+    R0: Accumulate,
+{
+    unsafe { &mut *(resource as *mut R0) }.combine(partial);
+}
+
+impl<'a, T, M0, R0> SystemParam<'a, T, M0> for Accumulator<'a, R0>
+where
+    T: Contains<R0, M0>,
+    R0: Accumulate,
+{
+    type State = Vec<Mutex<Option<R0>>>;
+
+    fn init_state() -> Self::State {
+        Vec::new()
+    }
+
+    fn declare_access(access: &mut SystemAccess) {
+        #[cfg(feature = "resources-interop")]
+        access.required_resources.push(T::index());
+        access.outputs.push(T::index());
+    }
+
+    fn fetch(state: &'a mut Self::State, resources: &'a T, _: &'a World) -> Self {
+        #[cfg(feature = "parallel")]
+        let partials = rayon::current_num_threads() + 1;
+        #[cfg(not(feature = "parallel"))]
+        let partials = 1;
+        state.resize_with(partials, || Mutex::new(None));
+        Accumulator {
+            partials: Partials::Borrowed(state),
+            resources: resources as *const T as *const (),
+            accumulate: accumulate_into::<T, M0, R0>,
+        }
+    }
+
+    unsafe fn release(_: &'a T) {}
+}
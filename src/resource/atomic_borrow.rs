@@ -15,15 +15,35 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 
-pub struct AtomicBorrow(AtomicUsize);
+/// Runtime borrow checking for a resource slot of an executor; also tracks change ticks
+/// of the resource: the tick of the current run, and of the last run it was changed in.
+pub struct AtomicBorrow(AtomicUsize, AtomicU64, AtomicU64);
 
 impl AtomicBorrow {
-    const UNIQUE_BIT: usize = !(usize::max_value() >> 1);
+    const UNIQUE_BIT: usize = !(usize::MAX >> 1);
 
     pub const fn new() -> Self {
-        Self(AtomicUsize::new(0))
+        // Runs are numbered from 1; change tick of 0 means "never changed".
+        Self(AtomicUsize::new(0), AtomicU64::new(1), AtomicU64::new(0))
+    }
+
+    pub fn tick(&self) -> u64 {
+        self.1.load(Ordering::Acquire)
+    }
+
+    pub fn set_tick(&mut self, tick: u64) {
+        *self.1.get_mut() = tick;
+    }
+
+    pub fn change_tick(&self) -> u64 {
+        self.2.load(Ordering::Acquire)
+    }
+
+    /// Called on every mutable borrow, whether or not the resource is then written to.
+    pub fn mark_changed(&self) {
+        self.2.store(self.tick(), Ordering::Release);
     }
 
     pub fn is_free(&self) -> bool {
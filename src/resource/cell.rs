@@ -1,12 +1,72 @@
-use std::{ptr::NonNull, thread::panicking};
+use parking_lot::{MappedMutexGuard, Mutex, MutexGuard};
+use std::{
+    any::TypeId,
+    ptr::NonNull,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread::{self, panicking, ThreadId},
+};
 
-use super::AtomicBorrow;
+use super::{
+    lock::{Lock, Locked},
+    AtomicBorrow,
+};
+use crate::executor::InSystem;
+
+/// Where the resource pointed to by a cell lives.
+enum Source<R0> {
+    Missing,
+    Pointer(NonNull<R0>),
+    /// Lock is acquired on borrow, and released once all of the borrows
+    /// on the same thread are released.
+    Lock {
+        lock: NonNull<dyn Lock<Resource = R0>>,
+        // Guards are dropped with the cell, so locks are released even if a release is skipped.
+        held: Mutex<Vec<Held<R0>>>,
+    },
+}
+
+/// The lock a resource lives behind, acquired by a borrow of it on a thread,
+/// and the number of borrows on that thread sharing it.
+struct Held<R0> {
+    thread: ThreadId,
+    locked: Locked<'static, R0>,
+    borrows: usize,
+}
+
+/// Partial values contributed by accumulators during a run, already combined together,
+/// and the function to combine them into the resource with when the cell is dropped.
+struct Accumulated<R0> {
+    value: R0,
+    combine: fn(&mut R0, R0),
+}
+
+/// Borrows of fields of a resource, on top of the shared borrow of the whole resource
+/// each of them takes: the field type and the number of shared borrows of it,
+/// or `FIELD_MUT` if it's borrowed mutably.
+type FieldBorrows = Vec<(TypeId, usize)>;
+
+const FIELD_MUT: usize = usize::MAX;
+
+const FIELDS_MUT_BIT: usize = !(usize::MAX >> 1);
+
+/// State of a cell only some resources need, allocated on first use.
+struct Extension<R0> {
+    fields: FieldBorrows,
+    accumulated: Option<Accumulated<R0>>,
+    finalize: Option<fn(&mut R0)>,
+}
 
 /// A pointer to a resource, with runtime borrow checking via an `AtomicBorrow`,
 /// accessed through a pointer to a cached one in an executor.
 pub struct ResourceCell<R0> {
-    cell: NonNull<R0>,
+    source: Source<R0>,
     borrow: NonNull<AtomicBorrow>,
+    mutable: bool,
+    // Shared borrows of the whole resource, or, with `FIELDS_MUT_BIT` set, mutable borrows
+    // of it's fields; these conflict with each other, but not with shared borrows
+    // of the `AtomicBorrow`.
+    whole_or_fields: AtomicUsize,
+    extension: Mutex<Option<Box<Extension<R0>>>>,
 }
 
 impl<R0> ResourceCell<R0> {
@@ -15,48 +75,456 @@ impl<R0> ResourceCell<R0> {
         R0: Send + Sync,
     {
         Self {
-            cell: NonNull::new(resource).expect("pointers to resources should never be null"),
+            source: Source::Pointer(
+                NonNull::new(resource).expect("pointers to resources should never be null"),
+            ),
+            borrow: NonNull::new(borrow).expect("pointers to AtomicBorrows should never be null"),
+            mutable: true,
+            whole_or_fields: AtomicUsize::new(0),
+            extension: Mutex::new(None),
+        }
+    }
+
+    /// Creates a cell that will panic if borrowed mutably.
+    pub fn new_shared(resource: &R0, borrow: &mut AtomicBorrow) -> Self
+    where
+        R0: Send + Sync,
+    {
+        Self {
+            source: Source::Pointer(NonNull::from(resource)),
+            borrow: NonNull::new(borrow).expect("pointers to AtomicBorrows should never be null"),
+            mutable: false,
+            whole_or_fields: AtomicUsize::new(0),
+            extension: Mutex::new(None),
+        }
+    }
+
+    /// Creates a cell for a resource that is not available; it will panic if borrowed.
+    pub fn new_missing(borrow: &mut AtomicBorrow) -> Self
+    where
+        R0: Send + Sync,
+    {
+        Self {
+            source: Source::Missing,
+            borrow: NonNull::new(borrow).expect("pointers to AtomicBorrows should never be null"),
+            mutable: true,
+            whole_or_fields: AtomicUsize::new(0),
+            extension: Mutex::new(None),
+        }
+    }
+
+    /// Creates a cell that will lock the resource only while it's borrowed.
+    pub fn new_lock<L>(lock: &L, borrow: &mut AtomicBorrow) -> Self
+    where
+        L: Lock<Resource = R0>,
+        R0: Send + Sync,
+    {
+        let lock = NonNull::from(lock as &dyn Lock<Resource = R0>);
+        Self {
+            source: Source::Lock {
+                // Cells never outlive the references they are made from.
+                lock: unsafe {
+                    std::mem::transmute::<
+                        NonNull<dyn Lock<Resource = R0> + '_>,
+                        NonNull<dyn Lock<Resource = R0>>,
+                    >(lock)
+                },
+                held: Mutex::new(Vec::new()),
+            },
             borrow: NonNull::new(borrow).expect("pointers to AtomicBorrows should never be null"),
+            mutable: true,
+            whole_or_fields: AtomicUsize::new(0),
+            extension: Mutex::new(None),
+        }
+    }
+
+    pub fn is_present(&self) -> bool {
+        !matches!(self.source, Source::Missing)
+    }
+
+    pub fn is_shared(&self) -> bool {
+        !self.mutable
+    }
+
+    pub fn is_locked(&self) -> bool {
+        matches!(self.source, Source::Lock { .. })
+    }
+
+    /// Returns `true` if the resource lives behind a lock that shared borrows
+    /// on different threads can't hold at the same time.
+    pub fn is_exclusive(&self) -> bool {
+        match &self.source {
+            Source::Lock { lock, .. } => unsafe { lock.as_ref() }.is_exclusive(),
+            _ => false,
+        }
+    }
+
+    /// Returns the extension of the cell, allocating it if needed.
+    fn extension(&self) -> MappedMutexGuard<'_, Extension<R0>> {
+        MutexGuard::map(self.extension.lock(), |extension| {
+            &mut **extension.get_or_insert_with(|| {
+                Box::new(Extension {
+                    fields: Vec::new(),
+                    accumulated: None,
+                    finalize: None,
+                })
+            })
+        })
+    }
+
+    /// Acquires the lock the resource lives behind, unless it's already held by
+    /// a shared borrow on the current thread; releases the borrow and panics
+    /// if the lock is poisoned.
+    fn acquire(
+        &self,
+        lock: &NonNull<dyn Lock<Resource = R0>>,
+        held: &Mutex<Vec<Held<R0>>>,
+        mutable: bool,
+    ) -> NonNull<R0> {
+        let thread = thread::current().id();
+        if !mutable {
+            let mut held = held.lock();
+            if let Some(held) = held.iter_mut().find(|held| held.thread == thread) {
+                held.borrows += 1;
+                return held.locked.resource;
+            }
+        }
+        // Not holding on to `held` while blocked, so that other threads can release
+        // their guards in the meantime.
+        match unsafe { lock.as_ref() }.acquire(mutable) {
+            Some(locked) => {
+                let resource = locked.resource;
+                held.lock().push(Held {
+                    thread,
+                    // The guard is dropped before the cell is.
+                    locked: unsafe {
+                        std::mem::transmute::<Locked<'_, R0>, Locked<'static, R0>>(locked)
+                    },
+                    borrows: 1,
+                });
+                resource
+            }
+            None => {
+                unsafe {
+                    if mutable {
+                        self.borrow.as_ref().release_mut();
+                    } else {
+                        self.whole_or_fields.fetch_sub(1, Ordering::AcqRel);
+                        self.borrow.as_ref().release();
+                    }
+                }
+                panic!(
+                    "cannot borrow {}{}: lock is poisoned{}",
+                    std::any::type_name::<R0>(),
+                    if mutable { " mutably" } else { " immutably" },
+                    InSystem
+                );
+            }
+        }
+    }
+
+    /// Drops the guard of the lock the resource lives behind once it's not borrowed
+    /// on the current thread; borrows are always released on the thread they were made on.
+    fn unlock(&self) {
+        let held = match &self.source {
+            Source::Lock { held, .. } => held,
+            _ => return,
+        };
+        let thread = thread::current().id();
+        let mut held = held.lock();
+        if let Some(index) = held.iter().position(|held| held.thread == thread) {
+            held[index].borrows -= 1;
+            if held[index].borrows == 0 {
+                held.swap_remove(index);
+            }
         }
     }
 
     pub fn borrow(&self) -> &R0 {
+        if let Source::Missing = self.source {
+            panic!(
+                "cannot borrow {} immutably: no such resource{}",
+                std::any::type_name::<R0>(),
+                InSystem
+            );
+        }
         assert!(
             unsafe { self.borrow.as_ref().borrow() },
-            "cannot borrow {} immutably: already borrowed mutably",
-            std::any::type_name::<R0>()
+            "cannot borrow {} immutably: already borrowed mutably{}",
+            std::any::type_name::<R0>(),
+            InSystem
         );
-        unsafe { self.cell.as_ref() }
+        let whole =
+            self.whole_or_fields
+                .fetch_update(Ordering::AcqRel, Ordering::Acquire, |state| {
+                    if state & FIELDS_MUT_BIT == 0 {
+                        Some(state + 1)
+                    } else {
+                        None
+                    }
+                });
+        if whole.is_err() {
+            unsafe { self.borrow.as_ref() }.release();
+            panic!(
+                "cannot borrow {} immutably: a field of it is already borrowed mutably{}",
+                std::any::type_name::<R0>(),
+                InSystem
+            );
+        }
+        match &self.source {
+            Source::Missing => unreachable!(),
+            Source::Pointer(pointer) => unsafe { &*pointer.as_ptr() },
+            Source::Lock { lock, held } => unsafe { &*self.acquire(lock, held, false).as_ptr() },
+        }
     }
 
     #[allow(clippy::mut_from_ref)]
     pub fn borrow_mut(&self) -> &mut R0 {
+        if let Source::Missing = self.source {
+            panic!(
+                "cannot borrow {} mutably: no such resource{}",
+                std::any::type_name::<R0>(),
+                InSystem
+            );
+        }
+        assert!(
+            self.mutable,
+            "cannot borrow {} mutably: executor was given a shared reference to it{}",
+            std::any::type_name::<R0>(),
+            InSystem
+        );
         assert!(
             unsafe { self.borrow.as_ref().borrow_mut() },
-            "cannot borrow {} mutably: already borrowed",
-            std::any::type_name::<R0>()
+            "cannot borrow {} mutably: already borrowed{}",
+            std::any::type_name::<R0>(),
+            InSystem
         );
-        unsafe { &mut *self.cell.clone().as_ptr() }
+        let pointer = match &self.source {
+            Source::Missing => unreachable!(),
+            Source::Pointer(pointer) => *pointer,
+            Source::Lock { lock, held } => self.acquire(lock, held, true),
+        };
+        unsafe { self.borrow.as_ref() }.mark_changed();
+        unsafe { &mut *pointer.as_ptr() }
+    }
+
+    /// Borrows the resource immutably, returning a pointer that the field of given type
+    /// may be accessed through; fields borrowed mutably are checked to be disjoint from
+    /// other borrowed fields and from shared borrows of the whole resource.
+    /// Released with `release_field()`.
+    pub fn borrow_for_field(&self, field: TypeId, mutable: bool) -> *mut R0 {
+        let pointer = match self.source {
+            Source::Missing => panic!(
+                "cannot borrow a field of {}: no such resource{}",
+                std::any::type_name::<R0>(),
+                InSystem
+            ),
+            Source::Pointer(pointer) => pointer,
+            Source::Lock { .. } => panic!(
+                "cannot borrow a field of {}: resources behind a lock can only be borrowed whole{}",
+                std::any::type_name::<R0>(),
+                InSystem
+            ),
+        };
+        assert!(
+            !mutable || self.mutable,
+            "cannot borrow a field of {} mutably: executor was given a shared reference to it{}",
+            std::any::type_name::<R0>(),
+            InSystem
+        );
+        assert!(
+            unsafe { self.borrow.as_ref().borrow() },
+            "cannot borrow a field of {}: already borrowed mutably{}",
+            std::any::type_name::<R0>(),
+            InSystem
+        );
+        let mut extension = self.extension();
+        let fields = &mut extension.fields;
+        let borrowed = fields.iter_mut().find(|(borrowed, _)| *borrowed == field);
+        let conflict = match (borrowed, mutable) {
+            (Some(_), true) => Some("already borrowed"),
+            (Some((_, FIELD_MUT)), false) => Some("already borrowed mutably"),
+            (Some((_, shared)), false) => {
+                *shared += 1;
+                None
+            }
+            (None, true) => {
+                let fields_mut = self.whole_or_fields.fetch_update(
+                    Ordering::AcqRel,
+                    Ordering::Acquire,
+                    |state| {
+                        if state == 0 || state & FIELDS_MUT_BIT != 0 {
+                            Some((state | FIELDS_MUT_BIT) + 1)
+                        } else {
+                            None
+                        }
+                    },
+                );
+                if fields_mut.is_ok() {
+                    fields.push((field, FIELD_MUT));
+                    None
+                } else {
+                    Some("the whole of it is already borrowed immutably")
+                }
+            }
+            (None, false) => {
+                fields.push((field, 1));
+                None
+            }
+        };
+        drop(extension);
+        if let Some(conflict) = conflict {
+            unsafe { self.borrow.as_ref() }.release();
+            panic!(
+                "cannot borrow a field of {}{}: {}{}",
+                std::any::type_name::<R0>(),
+                if mutable { " mutably" } else { "" },
+                conflict,
+                InSystem
+            );
+        }
+        if mutable {
+            unsafe { self.borrow.as_ref() }.mark_changed();
+        }
+        pointer.as_ptr()
+    }
+
+    /// Combines a partial value into ones contributed earlier in the run; the result is
+    /// combined into the resource itself when the cell is dropped.
+    pub fn accumulate(&self, partial: R0, combine: fn(&mut R0, R0)) {
+        match self.source {
+            Source::Missing => panic!(
+                "cannot accumulate into {}: no such resource{}",
+                std::any::type_name::<R0>(),
+                InSystem
+            ),
+            _ => assert!(
+                self.mutable,
+                "cannot accumulate into {}: executor was given a shared reference to it{}",
+                std::any::type_name::<R0>(),
+                InSystem
+            ),
+        }
+        let accumulated = &mut self.extension().accumulated;
+        match accumulated {
+            Some(accumulated) => combine(&mut accumulated.value, partial),
+            None => {
+                *accumulated = Some(Accumulated {
+                    value: partial,
+                    combine,
+                })
+            }
+        }
+    }
+
+    /// Sets a function to be called on the resource when the cell is dropped,
+    /// after accumulated values are combined into it.
+    pub fn finalize_with(&self, finalize: fn(&mut R0)) {
+        match self.source {
+            Source::Missing => panic!(
+                "cannot finalize {}: no such resource{}",
+                std::any::type_name::<R0>(),
+                InSystem
+            ),
+            _ => assert!(
+                self.mutable,
+                "cannot finalize {}: executor was given a shared reference to it{}",
+                std::any::type_name::<R0>(),
+                InSystem
+            ),
+        }
+        self.extension().finalize = Some(finalize);
+    }
+
+    /// Tick of the current run of the executor.
+    pub fn tick(&self) -> u64 {
+        unsafe { self.borrow.as_ref() }.tick()
+    }
+
+    /// Tick of the last run the resource was borrowed mutably in, or 0 if it never was.
+    pub fn change_tick(&self) -> u64 {
+        unsafe { self.borrow.as_ref() }.change_tick()
     }
 
     pub unsafe fn release(&self) {
+        self.whole_or_fields.fetch_sub(1, Ordering::AcqRel);
+        self.unlock();
+        self.borrow.as_ref().release();
+    }
+
+    pub unsafe fn release_field(&self, field: TypeId, mutable: bool) {
+        let mut extension = self.extension();
+        let fields = &mut extension.fields;
+        let index = fields
+            .iter()
+            .position(|(borrowed, _)| *borrowed == field)
+            .expect("released field should be borrowed");
+        match fields[index].1 {
+            FIELD_MUT | 1 => {
+                fields.swap_remove(index);
+            }
+            _ => fields[index].1 -= 1,
+        }
+        drop(extension);
+        if mutable {
+            self.whole_or_fields
+                .fetch_update(Ordering::AcqRel, Ordering::Acquire, |state| {
+                    let count = (state & !FIELDS_MUT_BIT) - 1;
+                    Some(if count == 0 {
+                        0
+                    } else {
+                        count | FIELDS_MUT_BIT
+                    })
+                })
+                .expect("closure always returns a value");
+        }
         self.borrow.as_ref().release();
     }
 
     pub unsafe fn release_mut(&self) {
+        self.unlock();
         self.borrow.as_ref().release_mut();
     }
 }
 
 impl<R0> Drop for ResourceCell<R0> {
     fn drop(&mut self) {
+        if panicking() {
+            return;
+        }
         #[cfg(debug_assertions)]
-        if !panicking() {
-            assert!(
-                unsafe { self.borrow.as_ref().is_free() },
-                "borrows of {} were not released properly",
-                std::any::type_name::<R0>()
-            )
+        assert!(
+            unsafe { self.borrow.as_ref().is_free() },
+            "borrows of {} were not released properly",
+            std::any::type_name::<R0>()
+        );
+        let (accumulated, finalize) = match self.extension.get_mut().take() {
+            Some(extension) => (extension.accumulated, extension.finalize),
+            None => return,
+        };
+        if accumulated.is_none() && finalize.is_none() {
+            return;
+        }
+        unsafe { self.borrow.as_ref() }.mark_changed();
+        let finish = |resource: &mut R0| {
+            if let Some(accumulated) = accumulated {
+                let Accumulated { value, combine } = accumulated;
+                combine(resource, value);
+            }
+            if let Some(finalize) = finalize {
+                finalize(resource);
+            }
+        };
+        match &self.source {
+            Source::Missing => unreachable!(),
+            Source::Pointer(pointer) => finish(unsafe { &mut *pointer.as_ptr() }),
+            Source::Lock { lock, .. } => match unsafe { lock.as_ref() }.acquire(true) {
+                Some(locked) => finish(unsafe { &mut *locked.resource.as_ptr() }),
+                None => panic!(
+                    "cannot finalize {}: lock is poisoned",
+                    std::any::type_name::<R0>()
+                ),
+            },
         }
     }
 }
@@ -0,0 +1,111 @@
+use hecs::World;
+use std::marker::PhantomData;
+
+use super::Contains;
+use crate::{SystemAccess, SystemParam};
+
+/// Change ticks of a resource of type `R`; used as a [`SystemParam`](trait.SystemParam.html)
+/// in the resources argument of a system.
+///
+/// Doesn't borrow the resource, and so doesn't conflict with any other system.
+/// A resource is considered changed in a run if a system borrowed it mutably, whether
+/// or not it actually wrote to it; see
+/// [`Executor::change_tick()`](struct.Executor.html#method.change_tick).
+///
+/// Changes to the resource will cause a reactive system to run, even if it borrows
+/// the resource mutably or not at all; see
+/// [`ExecutorBuilder::reactive()`](struct.ExecutorBuilder.html#method.reactive).
+///
+/// # Example
+/// ```rust
+/// # use yaks::{Changed, Executor};
+/// # let world = hecs::World::new();
+/// let mut executor = Executor::<(f32, u32)>::builder()
+///     .system(|_context, (some_f32, changed): (&mut f32, Changed<u32>), _queries: ()| {
+///         if changed.is_changed() {
+///             *some_f32 += 1.0;
+///         }
+///     })
+///     .build();
+/// let mut some_f32 = 0f32;
+/// let mut some_u32 = 0u32;
+/// executor.run(&world, (&mut some_f32, &mut some_u32));
+/// assert_eq!(some_f32, 0.0);
+/// ```
+pub struct Changed<R> {
+    tick: u64,
+    change_tick: u64,
+    observed: Option<u64>,
+    phantom_data: PhantomData<fn() -> R>,
+}
+
+impl<R> Changed<R> {
+    /// Creates change ticks of a resource, for calling systems as plain functions;
+    /// see [`System`](trait.System.html). The system will be treated as running
+    /// for the first time.
+    pub fn new(tick: u64, change_tick: u64) -> Self {
+        Self {
+            tick,
+            change_tick,
+            observed: None,
+            phantom_data: PhantomData,
+        }
+    }
+
+    /// Returns the tick of the current run of the executor.
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// Returns the tick of the latest run in which the resource was changed,
+    /// or 0 if it never was.
+    pub fn change_tick(&self) -> u64 {
+        self.change_tick
+    }
+
+    /// Returns `true` if the resource was changed in any run after the given tick,
+    /// including the current one.
+    pub fn changed_since(&self, tick: u64) -> bool {
+        self.change_tick > tick
+    }
+
+    /// Returns `true` if the resource was changed since the previous run of this system
+    /// observed it's change tick, or, if the system is running for the first time, ever.
+    ///
+    /// Changes are tracked per run, so this also reports changes made by the system
+    /// itself in it's previous run, and ones made by systems that ran after it; several
+    /// changes within the same run are reported once.
+    pub fn is_changed(&self) -> bool {
+        match self.observed {
+            Some(observed) => self.change_tick > observed,
+            None => self.change_tick != 0,
+        }
+    }
+}
+
+impl<'a, T, M0, R0> SystemParam<'a, T, M0> for Changed<R0>
+where
+    T: Contains<R0, M0>,
+{
+    type State = Option<u64>;
+
+    fn init_state() -> Self::State {
+        None
+    }
+
+    fn declare_access(access: &mut SystemAccess) {
+        access.inputs.push(T::index());
+    }
+
+    fn fetch(state: &'a mut Self::State, resources: &'a T, _: &'a World) -> Self {
+        let change_tick = resources.change_tick();
+        Changed {
+            tick: resources.tick(),
+            change_tick,
+            observed: state.replace(change_tick),
+            phantom_data: PhantomData,
+        }
+    }
+
+    unsafe fn release(_: &'a T) {}
+}
@@ -1,24 +1,40 @@
-#[cfg(feature = "parallel")]
-use fixedbitset::FixedBitSet;
+use std::any::TypeId;
 
 use super::ResourceCell;
 
 /// Specifies how a specific type may be borrowed from a tuple of cells.
 pub trait Contains<R0, M0> {
+    fn is_present(&self) -> bool;
+
     fn borrow(&self) -> &R0;
 
     #[allow(clippy::mut_from_ref)]
     fn borrow_mut(&self) -> &mut R0;
 
+    fn borrow_for_field(&self, field: TypeId, mutable: bool) -> *mut R0;
+
+    fn accumulate(&self, partial: R0, combine: fn(&mut R0, R0));
+
+    fn finalize_with(&self, finalize: fn(&mut R0));
+
+    fn tick(&self) -> u64;
+
+    fn change_tick(&self) -> u64;
+
     unsafe fn release(&self);
 
+    unsafe fn release_field(&self, field: TypeId, mutable: bool);
+
     unsafe fn release_mut(&self);
 
-    #[cfg(feature = "parallel")]
-    fn set_resource_bit(bitset: &mut FixedBitSet);
+    fn index() -> usize;
 }
 
 impl<R0> Contains<R0, ()> for (ResourceCell<R0>,) {
+    fn is_present(&self) -> bool {
+        self.0.is_present()
+    }
+
     fn borrow(&self) -> &R0 {
         self.0.borrow()
     }
@@ -27,29 +43,40 @@ impl<R0> Contains<R0, ()> for (ResourceCell<R0>,) {
         self.0.borrow_mut()
     }
 
+    fn borrow_for_field(&self, field: TypeId, mutable: bool) -> *mut R0 {
+        self.0.borrow_for_field(field, mutable)
+    }
+
+    fn accumulate(&self, partial: R0, combine: fn(&mut R0, R0)) {
+        self.0.accumulate(partial, combine);
+    }
+
+    fn finalize_with(&self, finalize: fn(&mut R0)) {
+        self.0.finalize_with(finalize);
+    }
+
+    fn tick(&self) -> u64 {
+        self.0.tick()
+    }
+
+    fn change_tick(&self) -> u64 {
+        self.0.change_tick()
+    }
+
     unsafe fn release(&self) {
         self.0.release();
     }
 
+    unsafe fn release_field(&self, field: TypeId, mutable: bool) {
+        self.0.release_field(field, mutable);
+    }
+
     unsafe fn release_mut(&self) {
         self.0.release_mut();
     }
 
-    #[cfg(feature = "parallel")]
-    fn set_resource_bit(bitset: &mut FixedBitSet) {
-        bitset.insert(0);
-    }
-}
-
-macro_rules! swap_to_unit {
-    ($anything:tt) => {
-        ()
-    };
-}
-
-macro_rules! swap_to_markers {
-    ($($letter:ident),*) => {
-        ($( swap_to_unit!($letter), )*)
+    fn index() -> usize {
+        0
     }
 }
 
@@ -63,6 +90,11 @@ macro_rules! impl_contains {
         impl<$($all),*> Contains<$letter, ($letter, swap_to_markers!($($tail),*))>
             for ($(ResourceCell<$all>,)*)
         {
+            fn is_present(&self) -> bool {
+                let ($($all,)*) = self;
+                $letter.is_present()
+            }
+
             fn borrow(&self) -> &$letter {
                 let ($($all,)*) = self;
                 $letter.borrow()
@@ -73,19 +105,48 @@ macro_rules! impl_contains {
                 $letter.borrow_mut()
             }
 
+            fn borrow_for_field(&self, field: TypeId, mutable: bool) -> *mut $letter {
+                let ($($all,)*) = self;
+                $letter.borrow_for_field(field, mutable)
+            }
+
+            fn accumulate(&self, partial: $letter, combine: fn(&mut $letter, $letter)) {
+                let ($($all,)*) = self;
+                $letter.accumulate(partial, combine);
+            }
+
+            fn finalize_with(&self, finalize: fn(&mut $letter)) {
+                let ($($all,)*) = self;
+                $letter.finalize_with(finalize);
+            }
+
+            fn tick(&self) -> u64 {
+                let ($($all,)*) = self;
+                $letter.tick()
+            }
+
+            fn change_tick(&self) -> u64 {
+                let ($($all,)*) = self;
+                $letter.change_tick()
+            }
+
             unsafe fn release(&self) {
                 let ($($all,)*) = self;
                 $letter.release();
             }
 
+            unsafe fn release_field(&self, field: TypeId, mutable: bool) {
+                let ($($all,)*) = self;
+                $letter.release_field(field, mutable);
+            }
+
             unsafe fn release_mut(&self) {
                 let ($($all,)*) = self;
                 $letter.release_mut();
             }
 
-            #[cfg(feature = "parallel")]
-            fn set_resource_bit(bitset: &mut FixedBitSet) {
-                bitset.insert(count!($($all)*) - (1usize + count!($($tail)*)));
+            fn index() -> usize {
+                count!($($all)*) - (1usize + count!($($tail)*))
             }
         }
         impl_contains!($($all),* ; $($tail),*);
@@ -96,6 +157,11 @@ macro_rules! impl_contains {
         impl<$($all),*> Contains<$letter, ($letter, )>
             for ($(ResourceCell<$all>,)*)
         {
+            fn is_present(&self) -> bool {
+                let ($($all,)*) = self;
+                $letter.is_present()
+            }
+
             fn borrow(&self) -> &$letter {
                 let ($($all,)*) = self;
                 $letter.borrow()
@@ -106,19 +172,48 @@ macro_rules! impl_contains {
                 $letter.borrow_mut()
             }
 
+            fn borrow_for_field(&self, field: TypeId, mutable: bool) -> *mut $letter {
+                let ($($all,)*) = self;
+                $letter.borrow_for_field(field, mutable)
+            }
+
+            fn accumulate(&self, partial: $letter, combine: fn(&mut $letter, $letter)) {
+                let ($($all,)*) = self;
+                $letter.accumulate(partial, combine);
+            }
+
+            fn finalize_with(&self, finalize: fn(&mut $letter)) {
+                let ($($all,)*) = self;
+                $letter.finalize_with(finalize);
+            }
+
+            fn tick(&self) -> u64 {
+                let ($($all,)*) = self;
+                $letter.tick()
+            }
+
+            fn change_tick(&self) -> u64 {
+                let ($($all,)*) = self;
+                $letter.change_tick()
+            }
+
             unsafe fn release(&self) {
                 let ($($all,)*) = self;
                 $letter.release();
             }
 
+            unsafe fn release_field(&self, field: TypeId, mutable: bool) {
+                let ($($all,)*) = self;
+                $letter.release_field(field, mutable);
+            }
+
             unsafe fn release_mut(&self) {
                 let ($($all,)*) = self;
                 $letter.release_mut();
             }
 
-            #[cfg(feature = "parallel")]
-            fn set_resource_bit(bitset: &mut FixedBitSet) {
-                bitset.insert(count!($($all)*) - 1usize);
+            fn index() -> usize {
+                count!($($all)*) - 1usize
             }
         }
     }
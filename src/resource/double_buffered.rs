@@ -0,0 +1,221 @@
+use hecs::World;
+use std::{
+    any::TypeId,
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+};
+
+use super::Contains;
+use crate::{SystemAccess, SystemParam};
+
+/// A resource holding two values of type `R`: the front buffer, read by systems via
+/// [`Front`](struct.Front.html), and the back buffer, written to by systems via
+/// [`Back`](struct.Back.html). Readers and writers don't conflict with each other.
+///
+/// After each run of an executor that has systems using either of the parameters,
+/// the buffers are swapped, even if none of said systems ran: values written during
+/// the run become visible to readers in the next one. Borrowing the whole resource
+/// mutably conflicts with both parameters, borrowing it immutably conflicts only with
+/// `Back`, and borrowing the back buffer twice conflicts as well.
+///
+/// The executor must be given a mutable reference to the resource, even if
+/// systems only read the front buffer.
+///
+/// # Example
+/// ```rust
+/// # use yaks::{Back, DoubleBuffered, Executor, Front};
+/// # let world = hecs::World::new();
+/// let mut positions = DoubleBuffered::new(vec![0.0f32; 4]);
+/// let mut executor = Executor::<(DoubleBuffered<Vec<f32>>,)>::builder()
+///     .system(|_context, (previous, mut next): (Front<Vec<f32>>, Back<Vec<f32>>), _queries: ()| {
+///         for (next, previous) in next.iter_mut().zip(previous.iter()) {
+///             *next = previous + 1.0;
+///         }
+///     })
+///     .build();
+/// executor.run(&world, &mut positions);
+/// executor.run(&world, &mut positions);
+/// assert_eq!(positions.front(), &vec![2.0; 4]);
+/// ```
+pub struct DoubleBuffered<R> {
+    front: R,
+    back: R,
+}
+
+impl<R> DoubleBuffered<R> {
+    /// Creates the resource with both buffers set to the value.
+    pub fn new(value: R) -> Self
+    where
+        R: Clone,
+    {
+        Self {
+            front: value.clone(),
+            back: value,
+        }
+    }
+
+    /// Creates the resource from distinct values of the buffers.
+    pub fn from_buffers(front: R, back: R) -> Self {
+        Self { front, back }
+    }
+
+    /// Returns the front buffer.
+    pub fn front(&self) -> &R {
+        &self.front
+    }
+
+    /// Returns the front buffer mutably.
+    pub fn front_mut(&mut self) -> &mut R {
+        &mut self.front
+    }
+
+    /// Returns the back buffer.
+    pub fn back(&self) -> &R {
+        &self.back
+    }
+
+    /// Returns the back buffer mutably.
+    pub fn back_mut(&mut self) -> &mut R {
+        &mut self.back
+    }
+
+    /// Swaps the buffers.
+    pub fn swap(&mut self) {
+        std::mem::swap(&mut self.front, &mut self.back);
+    }
+}
+
+impl<R> Default for DoubleBuffered<R>
+where
+    R: Default,
+{
+    fn default() -> Self {
+        Self::from_buffers(R::default(), R::default())
+    }
+}
+
+/// Has the buffers swapped when the executor's resource cells are dropped.
+fn swap_buffers<T, M0, R>(resources: *const ())
+where
+    T: Contains<DoubleBuffered<R>, M0>,
+{
+    unsafe { &*(resources as *const T) }.finalize_with(DoubleBuffered::swap);
+}
+
+// Stand-ins for field types of the buffers when tracking access.
+struct FrontBuffer<R>(PhantomData<R>);
+
+struct BackBuffer<R>(PhantomData<R>);
+
+/// Immutable borrow of the front buffer of a [`DoubleBuffered<R>`](struct.DoubleBuffered.html);
+/// used as a [`SystemParam`](trait.SystemParam.html) in the resources argument of a system.
+pub struct Front<'a, R>(&'a R);
+
+impl<'a, R> Front<'a, R> {
+    /// Borrows the front buffer, for calling systems as plain functions;
+    /// see [`System`](trait.System.html).
+    pub fn new(resource: &'a DoubleBuffered<R>) -> Self {
+        Self(&resource.front)
+    }
+}
+
+impl<R> Deref for Front<'_, R> {
+    type Target = R;
+
+    fn deref(&self) -> &Self::Target {
+        self.0
+    }
+}
+
+impl<'a, T, M0, R> SystemParam<'a, T, M0> for Front<'a, R>
+where
+    T: Contains<DoubleBuffered<R>, M0>,
+    R: 'static,
+{
+    type State = ();
+
+    fn init_state() -> Self::State {}
+
+    fn declare_access(access: &mut SystemAccess) {
+        #[cfg(feature = "parallel")]
+        access
+            .resource_field_set
+            .immutable
+            .insert((T::index(), TypeId::of::<FrontBuffer<R>>()));
+        #[cfg(feature = "resources-interop")]
+        access.required_resources.push(T::index());
+        access
+            .finalizers
+            .push((T::index(), swap_buffers::<T, M0, R>));
+        access.outputs.push(T::index());
+        access.inputs.push(T::index());
+    }
+
+    fn fetch(_: &'a mut Self::State, resources: &'a T, _: &'a World) -> Self {
+        let resource = T::borrow_for_field(resources, TypeId::of::<FrontBuffer<R>>(), false);
+        Front(unsafe { &*std::ptr::addr_of!((*resource).front) })
+    }
+
+    unsafe fn release(resources: &'a T) {
+        T::release_field(resources, TypeId::of::<FrontBuffer<R>>(), false);
+    }
+}
+
+/// Mutable borrow of the back buffer of a [`DoubleBuffered<R>`](struct.DoubleBuffered.html);
+/// used as a [`SystemParam`](trait.SystemParam.html) in the resources argument of a system.
+pub struct Back<'a, R>(&'a mut R);
+
+impl<'a, R> Back<'a, R> {
+    /// Borrows the back buffer, for calling systems as plain functions;
+    /// see [`System`](trait.System.html).
+    pub fn new(resource: &'a mut DoubleBuffered<R>) -> Self {
+        Self(&mut resource.back)
+    }
+}
+
+impl<R> Deref for Back<'_, R> {
+    type Target = R;
+
+    fn deref(&self) -> &Self::Target {
+        self.0
+    }
+}
+
+impl<R> DerefMut for Back<'_, R> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.0
+    }
+}
+
+impl<'a, T, M0, R> SystemParam<'a, T, M0> for Back<'a, R>
+where
+    T: Contains<DoubleBuffered<R>, M0>,
+    R: 'static,
+{
+    type State = ();
+
+    fn init_state() -> Self::State {}
+
+    fn declare_access(access: &mut SystemAccess) {
+        #[cfg(feature = "parallel")]
+        access
+            .resource_field_set
+            .mutable
+            .insert((T::index(), TypeId::of::<BackBuffer<R>>()));
+        #[cfg(feature = "resources-interop")]
+        access.required_resources.push(T::index());
+        access
+            .finalizers
+            .push((T::index(), swap_buffers::<T, M0, R>));
+        access.outputs.push(T::index());
+    }
+
+    fn fetch(_: &'a mut Self::State, resources: &'a T, _: &'a World) -> Self {
+        let resource = T::borrow_for_field(resources, TypeId::of::<BackBuffer<R>>(), true);
+        Back(unsafe { &mut *std::ptr::addr_of_mut!((*resource).back) })
+    }
+
+    unsafe fn release(resources: &'a T) {
+        T::release_field(resources, TypeId::of::<BackBuffer<R>>(), true);
+    }
+}
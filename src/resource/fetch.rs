@@ -1,107 +1,116 @@
-use super::Contains;
-
-#[cfg(feature = "parallel")]
-use crate::BorrowSet;
-
-/// Specifies how a tuple of types may be borrowed from a tuple of cells.
-pub trait Fetch<'a, T, M0>: Sized {
-    fn fetch(resources: &'a T) -> Self;
+use hecs::World;
 
-    unsafe fn release(resources: &'a T);
-
-    #[cfg(feature = "parallel")]
-    fn set_resource_bits(resource_set: &mut BorrowSet);
-}
+use super::Contains;
+use crate::{SystemAccess, SystemParam};
 
-impl<'a, T, M0, R0> Fetch<'a, T, M0> for &'a R0
+impl<'a, T, M0, R0> SystemParam<'a, T, M0> for &'a R0
 where
     T: Contains<R0, M0>,
     R0: 'a,
 {
-    fn fetch(resources: &'a T) -> Self {
+    type State = ();
+
+    fn init_state() -> Self::State {}
+
+    fn declare_access(access: &mut SystemAccess) {
+        #[cfg(feature = "parallel")]
+        access.resource_set.immutable.insert(T::index());
+        #[cfg(feature = "resources-interop")]
+        access.required_resources.push(T::index());
+        access.inputs.push(T::index());
+    }
+
+    fn fetch(_: &'a mut Self::State, resources: &'a T, _: &'a World) -> Self {
         T::borrow(resources)
     }
 
     unsafe fn release(resources: &'a T) {
         T::release(resources);
     }
-
-    #[cfg(feature = "parallel")]
-    fn set_resource_bits(resource_set: &mut BorrowSet) {
-        T::set_resource_bit(&mut resource_set.immutable);
-    }
 }
 
-impl<'a, T, M0, R0> Fetch<'a, T, M0> for &'a mut R0
+impl<'a, T, M0, R0> SystemParam<'a, T, M0> for &'a mut R0
 where
     T: Contains<R0, M0>,
     R0: 'a,
 {
-    fn fetch(resources: &'a T) -> Self {
+    type State = ();
+
+    fn init_state() -> Self::State {}
+
+    fn declare_access(access: &mut SystemAccess) {
+        #[cfg(feature = "parallel")]
+        access.resource_set.mutable.insert(T::index());
+        #[cfg(feature = "resources-interop")]
+        access.required_resources.push(T::index());
+        access.outputs.push(T::index());
+    }
+
+    fn fetch(_: &'a mut Self::State, resources: &'a T, _: &'a World) -> Self {
         T::borrow_mut(resources)
     }
 
     unsafe fn release(resources: &'a T) {
         T::release_mut(resources);
     }
-
-    #[cfg(feature = "parallel")]
-    fn set_resource_bits(resource_set: &mut BorrowSet) {
-        T::set_resource_bit(&mut resource_set.mutable);
-    }
 }
 
-impl<'a, T> Fetch<'a, T, ()> for () {
-    fn fetch(_: &'a T) -> Self {}
+impl<'a, T, M0, R0> SystemParam<'a, T, M0> for Option<&'a R0>
+where
+    T: Contains<R0, M0>,
+    R0: 'a,
+{
+    type State = ();
+
+    fn init_state() -> Self::State {}
+
+    fn declare_access(access: &mut SystemAccess) {
+        #[cfg(feature = "parallel")]
+        access.resource_set.immutable.insert(T::index());
+        access.inputs.push(T::index());
+    }
 
-    unsafe fn release(_: &'a T) {}
+    fn fetch(_: &'a mut Self::State, resources: &'a T, _: &'a World) -> Self {
+        if T::is_present(resources) {
+            Some(T::borrow(resources))
+        } else {
+            None
+        }
+    }
 
-    #[cfg(feature = "parallel")]
-    fn set_resource_bits(_: &mut BorrowSet) {}
+    unsafe fn release(resources: &'a T) {
+        if T::is_present(resources) {
+            T::release(resources);
+        }
+    }
 }
 
-impl<'a, T, M0, F0> Fetch<'a, T, (M0,)> for (F0,)
+impl<'a, T, M0, R0> SystemParam<'a, T, M0> for Option<&'a mut R0>
 where
-    F0: Fetch<'a, T, M0>,
+    T: Contains<R0, M0>,
+    R0: 'a,
 {
-    fn fetch(resources: &'a T) -> Self {
-        (F0::fetch(resources),)
-    }
+    type State = ();
 
-    unsafe fn release(resources: &'a T) {
-        F0::release(resources);
+    fn init_state() -> Self::State {}
+
+    fn declare_access(access: &mut SystemAccess) {
+        #[cfg(feature = "parallel")]
+        access.resource_set.mutable.insert(T::index());
+        access.outputs.push(T::index());
     }
 
-    #[cfg(feature = "parallel")]
-    fn set_resource_bits(resource_set: &mut BorrowSet) {
-        F0::set_resource_bits(resource_set);
+    fn fetch(_: &'a mut Self::State, resources: &'a T, _: &'a World) -> Self {
+        if T::is_present(resources) {
+            Some(T::borrow_mut(resources))
+        } else {
+            None
+        }
     }
-}
 
-macro_rules! impl_fetch {
-    ($($letter:ident),*) => {
-        paste::item! {
-            impl<'a, T, $([<M $letter>],)* $([<F $letter>],)*> Fetch<'a, T, ($([<M $letter>],)*)>
-                for ($([<F $letter>]),*)
-            where
-                $([<F $letter>]: Fetch<'a, T, [<M $letter>]>,)*
-            {
-                fn fetch(resources: &'a T) -> Self {
-                    ($([<F $letter>]::fetch(resources)),*)
-                }
-
-                #[allow(non_snake_case)]
-                unsafe fn release(resources: &'a T) {
-                    $([<F $letter>]::release(resources);)*
-                }
-
-                #[cfg(feature = "parallel")]
-                fn set_resource_bits(resource_set: &mut BorrowSet) {
-                    $([<F $letter>]::set_resource_bits(resource_set);)*
-                }
-            }
+    unsafe fn release(resources: &'a T) {
+        if T::is_present(resources) {
+            T::release_mut(resources);
         }
     }
 }
-
-impl_for_tuples!(impl_fetch);
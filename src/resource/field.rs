@@ -0,0 +1,206 @@
+use hecs::World;
+use std::{
+    any::TypeId,
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+};
+
+use super::Contains;
+use crate::{SystemAccess, SystemParam};
+
+/// Specifies that a resource has a field of type `F` that may be borrowed independently
+/// of the rest of it, via [`Field`](struct.Field.html) and
+/// [`FieldMut`](struct.FieldMut.html) system parameters.
+///
+/// Should be implemented with the [`resource_fields!`](macro.resource_fields.html) macro.
+///
+/// # Safety
+/// Pointers returned for different field types of the same resource must not overlap,
+/// and must be derived from the given pointer without creating a reference to the whole
+/// resource; executor relies on this to run systems borrowing different fields mutably
+/// at the same time.
+pub unsafe trait ResourceField<F>: Send + Sync {
+    /// Returns a pointer to the field of type `F` of the resource behind the pointer.
+    ///
+    /// # Safety
+    /// The pointer must be valid.
+    unsafe fn field_ptr(resource: *mut Self) -> *mut F;
+}
+
+/// Implements [`ResourceField`](trait.ResourceField.html) for each of the listed fields
+/// of a resource; the fields must have distinct types.
+///
+/// Systems that borrow disjoint fields of a resource (via [`Field`](struct.Field.html)
+/// and [`FieldMut`](struct.FieldMut.html)) may run concurrently, even if they borrow
+/// them mutably; borrowing the whole resource borrows all of it's fields.
+///
+/// Within a single system, a field borrowed mutably can't be borrowed again, and can't be
+/// borrowed together with the whole resource; doing so panics when the system runs,
+/// same as `(&mut R, &R)` would.
+///
+/// # Example
+/// ```rust
+/// # use yaks::{Executor, Field, FieldMut};
+/// # let world = hecs::World::new();
+/// struct Physics(f32);
+/// struct Ui(u32);
+///
+/// struct GameState {
+///     physics: Physics,
+///     ui: Ui,
+/// }
+///
+/// yaks::resource_fields!(GameState {
+///     physics: Physics,
+///     ui: Ui,
+/// });
+///
+/// let mut state = GameState {
+///     physics: Physics(0.0),
+///     ui: Ui(0),
+/// };
+/// let mut executor = Executor::<(GameState,)>::builder()
+///     // These two systems may run concurrently.
+///     .system(|_context, mut physics: FieldMut<GameState, Physics>, _queries: ()| {
+///         physics.0 += 1.0;
+///     })
+///     .system(|_context, mut ui: FieldMut<GameState, Ui>, _queries: ()| {
+///         ui.0 += 1;
+///     })
+///     .build();
+/// executor.run(&world, &mut state);
+/// assert_eq!(state.ui.0, 1);
+/// ```
+#[macro_export]
+macro_rules! resource_fields {
+    ($resource:ty { $($field:ident: $field_type:ty),* $(,)? }) => {
+        $(
+            unsafe impl $crate::ResourceField<$field_type> for $resource {
+                unsafe fn field_ptr(resource: *mut Self) -> *mut $field_type {
+                    ::std::ptr::addr_of_mut!((*resource).$field)
+                }
+            }
+        )*
+    };
+}
+
+/// Immutable borrow of a field of type `F` of resource `R`; used as
+/// a [`SystemParam`](trait.SystemParam.html) in the resources argument of a system.
+///
+/// See [`resource_fields!`](macro.resource_fields.html).
+pub struct Field<'a, R, F>(&'a F, PhantomData<&'a R>);
+
+impl<'a, R, F> Field<'a, R, F>
+where
+    R: ResourceField<F>,
+{
+    /// Borrows the field from a resource, for calling systems as plain functions;
+    /// see [`System`](trait.System.html).
+    pub fn new(resource: &'a R) -> Self {
+        Self(
+            unsafe { &*R::field_ptr(resource as *const R as *mut R) },
+            PhantomData,
+        )
+    }
+}
+
+impl<R, F> Deref for Field<'_, R, F> {
+    type Target = F;
+
+    fn deref(&self) -> &Self::Target {
+        self.0
+    }
+}
+
+impl<'a, T, M0, R0, F> SystemParam<'a, T, M0> for Field<'a, R0, F>
+where
+    T: Contains<R0, M0>,
+    R0: ResourceField<F> + 'a,
+    F: 'static,
+{
+    type State = ();
+
+    fn init_state() -> Self::State {}
+
+    fn declare_access(access: &mut SystemAccess) {
+        #[cfg(feature = "parallel")]
+        access
+            .resource_field_set
+            .immutable
+            .insert((T::index(), TypeId::of::<F>()));
+        #[cfg(feature = "resources-interop")]
+        access.required_resources.push(T::index());
+        access.inputs.push(T::index());
+    }
+
+    fn fetch(_: &'a mut Self::State, resources: &'a T, _: &'a World) -> Self {
+        let resource = T::borrow_for_field(resources, TypeId::of::<F>(), false);
+        Field(unsafe { &*R0::field_ptr(resource) }, PhantomData)
+    }
+
+    unsafe fn release(resources: &'a T) {
+        T::release_field(resources, TypeId::of::<F>(), false);
+    }
+}
+
+/// Mutable borrow of a field of type `F` of resource `R`; used as
+/// a [`SystemParam`](trait.SystemParam.html) in the resources argument of a system.
+///
+/// See [`resource_fields!`](macro.resource_fields.html).
+pub struct FieldMut<'a, R, F>(&'a mut F, PhantomData<&'a mut R>);
+
+impl<'a, R, F> FieldMut<'a, R, F>
+where
+    R: ResourceField<F>,
+{
+    /// Borrows the field from a resource, for calling systems as plain functions;
+    /// see [`System`](trait.System.html).
+    pub fn new(resource: &'a mut R) -> Self {
+        Self(unsafe { &mut *R::field_ptr(resource) }, PhantomData)
+    }
+}
+
+impl<R, F> Deref for FieldMut<'_, R, F> {
+    type Target = F;
+
+    fn deref(&self) -> &Self::Target {
+        self.0
+    }
+}
+
+impl<R, F> DerefMut for FieldMut<'_, R, F> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.0
+    }
+}
+
+impl<'a, T, M0, R0, F> SystemParam<'a, T, M0> for FieldMut<'a, R0, F>
+where
+    T: Contains<R0, M0>,
+    R0: ResourceField<F> + 'a,
+    F: 'static,
+{
+    type State = ();
+
+    fn init_state() -> Self::State {}
+
+    fn declare_access(access: &mut SystemAccess) {
+        #[cfg(feature = "parallel")]
+        access
+            .resource_field_set
+            .mutable
+            .insert((T::index(), TypeId::of::<F>()));
+        #[cfg(feature = "resources-interop")]
+        access.required_resources.push(T::index());
+        access.outputs.push(T::index());
+    }
+
+    fn fetch(_: &'a mut Self::State, resources: &'a T, _: &'a World) -> Self {
+        let resource = T::borrow_for_field(resources, TypeId::of::<F>(), true);
+        FieldMut(unsafe { &mut *R0::field_ptr(resource) }, PhantomData)
+    }
+
+    unsafe fn release(resources: &'a T) {
+        T::release_field(resources, TypeId::of::<F>(), true);
+    }
+}
@@ -0,0 +1,73 @@
+/// Specifies how a specific type may be borrowed from a tuple of owned resources.
+pub trait Get<R0, M0> {
+    fn get(&self) -> &R0;
+
+    fn get_mut(&mut self) -> &mut R0;
+
+    fn index() -> usize;
+}
+
+impl<R0> Get<R0, ()> for (R0,) {
+    fn get(&self) -> &R0 {
+        &self.0
+    }
+
+    fn get_mut(&mut self) -> &mut R0 {
+        &mut self.0
+    }
+
+    fn index() -> usize {
+        0
+    }
+}
+
+macro_rules! impl_get {
+    ($($letter:ident),*) => {
+        impl_get!($($letter),* ; $($letter),*);
+    };
+    ($($all:ident),* ; $letter:ident, $($tail:ident),*) => {
+        #[allow(non_snake_case)]
+        #[allow(unused_variables)]
+        impl<$($all),*> Get<$letter, ($letter, swap_to_markers!($($tail),*))>
+            for ($($all,)*)
+        {
+            fn get(&self) -> &$letter {
+                let ($($all,)*) = self;
+                $letter
+            }
+
+            fn get_mut(&mut self) -> &mut $letter {
+                let ($($all,)*) = self;
+                $letter
+            }
+
+            fn index() -> usize {
+                count!($($all)*) - (1usize + count!($($tail)*))
+            }
+        }
+        impl_get!($($all),* ; $($tail),*);
+    };
+    ($($all:ident),* ; $letter:ident ) => {
+        #[allow(non_snake_case)]
+        #[allow(unused_variables)]
+        impl<$($all),*> Get<$letter, ($letter, )>
+            for ($($all,)*)
+        {
+            fn get(&self) -> &$letter {
+                let ($($all,)*) = self;
+                $letter
+            }
+
+            fn get_mut(&mut self) -> &mut $letter {
+                let ($($all,)*) = self;
+                $letter
+            }
+
+            fn index() -> usize {
+                count!($($all)*) - 1usize
+            }
+        }
+    }
+}
+
+impl_for_tuples!(impl_get);
@@ -0,0 +1,187 @@
+use std::ptr::NonNull;
+
+use super::{AtomicBorrow, ResourceCell, ResourceRef};
+
+/// Wraps a reference to a [`parking_lot::RwLock`][rw], a [`parking_lot::Mutex`][m],
+/// or their `std::sync` counterparts, allowing it to be used in place of a reference
+/// to the resource it contains when running an executor.
+///
+/// Instead of being locked for the whole run, the lock is acquired by each system that
+/// borrows the resource right before it runs, and released right after it returns
+/// (or panics): systems with `&R` acquire a read lock, systems with `&mut R` acquire
+/// a write lock, and a mutex is locked by either. Other threads are thus only blocked
+/// by the executor while a system that requires the resource is running.
+///
+/// Systems borrowing a resource behind a read-write lock immutably may run concurrently,
+/// each acquiring it's own read lock; since a mutex is locked by shared borrows as well,
+/// systems that borrow a resource behind one never run concurrently with each other.
+/// A system waiting for a lock held outside of the executor blocks the thread it runs on,
+/// keeping it from working on other systems and batches.
+///
+/// Locks are acquired in the order the resources appear in the system's signature;
+/// threads that hold one of the locks while waiting for another must acquire them
+/// in the same order to avoid deadlocks.
+///
+/// Fields of resources behind a lock can't be borrowed separately. A system borrowing
+/// a resource behind a poisoned `std::sync` lock panics.
+///
+/// [rw]: ../parking_lot/type.RwLock.html
+/// [m]: ../parking_lot/type.Mutex.html
+///
+/// # Example
+/// ```rust
+/// # use yaks::{Executor, LockOnDemand};
+/// # use std::sync::{Arc, Mutex};
+/// # let world = hecs::World::new();
+/// let shared_f32 = Arc::new(Mutex::new(0f32));
+/// let shared_u32 = Arc::new(parking_lot::RwLock::new(0u32));
+/// let mut executor = Executor::<(f32, u32)>::builder()
+///     .system(|_context, some_f32: &mut f32, _queries: ()| {
+///         *some_f32 += 1.0;
+///     })
+///     .system(|_context, some_u32: &mut u32, _queries: ()| {
+///         *some_u32 += 1;
+///     })
+///     .build();
+/// executor.run(
+///     &world,
+///     (LockOnDemand::new(&*shared_f32), LockOnDemand::new(&*shared_u32)),
+/// );
+/// assert_eq!(*shared_f32.lock().unwrap(), 1.0);
+/// assert_eq!(*shared_u32.read(), 1);
+/// ```
+pub struct LockOnDemand<'a, L>(&'a L);
+
+impl<'a, L> LockOnDemand<'a, L> {
+    /// Wraps a reference to a lock.
+    pub fn new(lock: &'a L) -> Self {
+        Self(lock)
+    }
+}
+
+impl<L> ResourceRef for LockOnDemand<'_, L>
+where
+    L: Lock,
+    L::Resource: Send + Sync,
+{
+    type Resource = L::Resource;
+
+    fn wrap_ref(&mut self, borrow: &mut AtomicBorrow) -> ResourceCell<L::Resource> {
+        ResourceCell::new_lock(self.0, borrow)
+    }
+}
+
+/// Type-erased guard of a lock, unlocking it when dropped.
+pub trait Guard {}
+
+impl<T> Guard for T {}
+
+/// An acquired lock: a pointer to the resource, valid while the guard is alive.
+pub struct Locked<'a, R0> {
+    pub resource: NonNull<R0>,
+    pub guard: Box<dyn Guard + 'a>,
+}
+
+/// A lock that can be wrapped in `LockOnDemand`.
+pub trait Lock: Sync {
+    type Resource;
+
+    /// Blocks until the lock is acquired, for writing if `mutable`;
+    /// returns `None` if the lock is poisoned.
+    fn acquire(&self, mutable: bool) -> Option<Locked<'_, Self::Resource>>;
+
+    /// Returns `true` if acquiring the lock for reading excludes other readers.
+    fn is_exclusive(&self) -> bool;
+}
+
+impl<R0> Lock for parking_lot::RwLock<R0>
+where
+    R0: Send + Sync,
+{
+    type Resource = R0;
+
+    fn acquire(&self, mutable: bool) -> Option<Locked<'_, R0>> {
+        Some(if mutable {
+            let mut guard = self.write();
+            Locked {
+                resource: NonNull::from(&mut *guard),
+                guard: Box::new(guard),
+            }
+        } else {
+            let guard = self.read();
+            Locked {
+                resource: NonNull::from(&*guard),
+                guard: Box::new(guard),
+            }
+        })
+    }
+
+    fn is_exclusive(&self) -> bool {
+        false
+    }
+}
+
+impl<R0> Lock for parking_lot::Mutex<R0>
+where
+    R0: Send + Sync,
+{
+    type Resource = R0;
+
+    fn acquire(&self, _: bool) -> Option<Locked<'_, R0>> {
+        let mut guard = self.lock();
+        Some(Locked {
+            resource: NonNull::from(&mut *guard),
+            guard: Box::new(guard),
+        })
+    }
+
+    fn is_exclusive(&self) -> bool {
+        true
+    }
+}
+
+impl<R0> Lock for std::sync::RwLock<R0>
+where
+    R0: Send + Sync,
+{
+    type Resource = R0;
+
+    fn acquire(&self, mutable: bool) -> Option<Locked<'_, R0>> {
+        Some(if mutable {
+            let mut guard = self.write().ok()?;
+            Locked {
+                resource: NonNull::from(&mut *guard),
+                guard: Box::new(guard),
+            }
+        } else {
+            let guard = self.read().ok()?;
+            Locked {
+                resource: NonNull::from(&*guard),
+                guard: Box::new(guard),
+            }
+        })
+    }
+
+    fn is_exclusive(&self) -> bool {
+        false
+    }
+}
+
+impl<R0> Lock for std::sync::Mutex<R0>
+where
+    R0: Send + Sync,
+{
+    type Resource = R0;
+
+    fn acquire(&self, _: bool) -> Option<Locked<'_, R0>> {
+        let mut guard = self.lock().ok()?;
+        Some(Locked {
+            resource: NonNull::from(&mut *guard),
+            guard: Box::new(guard),
+        })
+    }
+
+    fn is_exclusive(&self) -> bool {
+        true
+    }
+}
@@ -1,30 +1,73 @@
 //! Resource flow:
 //! - resources argument is passed to `Executor::<Tuple: ResourceTuple>::run()`,
 //! - tuple of references to types in `Tuple` is extracted
-//! from the argument (`RefExtractor`),
+//!   from the argument (`ResourceProvider`),
 //! - the references, together with `AtomicBorrow`s from the executor,
-//! are wrapped into `ResourceCell`s (`ResourceWrap`),
+//!   are wrapped into `ResourceCell`s (`ResourceWrap` for the whole tuple, `ResourceRef`
+//!   for each of it's elements; cells made from shared references refuse mutable borrows,
+//!   cells made from `LockOnDemand` acquire the lock on borrow and hold the guard until
+//!   the last borrow is released),
 //! - when each system in the executor is ran, a subset tuple of references matching
-//! that of the system's resources argument is fetched from the cells, setting runtime
-//! borrow checking (`Fetch` for the whole tuple, `Contains` for each of it's elements),
+//!   that of the system's resources argument is fetched from the cells, setting runtime
+//!   borrow checking (`SystemParam` for the whole tuple, `Contains` for each of it's elements),
 //! - the subset tuple of references is passed into the system's boxed closure,
-//! - after closure returns, the borrows are "released", resetting runtime
-//! borrow checking (`Fetch` and `Contains` again),
+//! - after closure returns (or panics), the borrows are "released", resetting runtime
+//!   borrow checking (`SystemParam` and `Contains` again),
 //! - after all of the systems have been ran, the cells are dropped.
+//!
+//! Accumulators don't borrow their resource while systems run; each keeps partial values
+//! in it's state, which are combined in the resource's cell when the system returns, and
+//! into the resource itself when the cell is dropped; buffers of `DoubleBuffered`
+//! resources are swapped and `Events` queues updated at that point as well, by finalizers
+//! the executor registers with the cells at the start of every run
+//! (`SystemAccess::finalizers`).
+//!
+//! `AtomicBorrow`s also hold the tick of the current run, and the tick of the last run
+//! their resource was borrowed mutably in; the executor advances the former after
+//! each run (`Changed`, `Executor::change_tick()`).
+//!
+//! Executors that own their resources skip extraction, wrapping the tuple of values
+//! they store directly (`ResourceTuple`), and lend them out between runs (`Get`).
 
+// Used by `Contains` and `Get` to spell out the marker types of their impls.
+macro_rules! swap_to_unit {
+    ($anything:tt) => {
+        ()
+    };
+}
+
+macro_rules! swap_to_markers {
+    ($($letter:ident),*) => {
+        ($( swap_to_unit!($letter), )*)
+    }
+}
+
+mod accumulator;
 mod atomic_borrow;
 mod cell;
+mod changed;
 mod contains;
+mod double_buffered;
 mod fetch;
-mod ref_extractor;
+mod field;
+mod get;
+mod lock;
+mod provider;
 mod tuple;
 mod wrap;
 
 use cell::ResourceCell;
-use contains::Contains;
 
+pub(crate) use contains::Contains;
+
+pub(crate) use accumulator::partial_index;
+pub use accumulator::{Accumulate, Accumulator};
 pub use atomic_borrow::AtomicBorrow;
-pub use fetch::Fetch;
-pub use ref_extractor::RefExtractor;
+pub use changed::Changed;
+pub use double_buffered::{Back, DoubleBuffered, Front};
+pub use field::{Field, FieldMut, ResourceField};
+pub use get::Get;
+pub use lock::LockOnDemand;
+pub use provider::ResourceProvider;
 pub use tuple::ResourceTuple;
-pub use wrap::ResourceWrap;
+pub use wrap::{ResourceRef, ResourceWrap};
@@ -0,0 +1,105 @@
+use hecs::World;
+
+use super::{ResourceRef, ResourceTuple, ResourceWrap};
+use crate::Executor;
+
+/// Specifies how resources of an executor are obtained from the implementor, allowing it
+/// to be used as the resources argument of [`Executor::run()`](struct.Executor.html#method.run).
+///
+/// Implemented for tuples of (mutable) references to the types in `Resources`, a single
+/// mutable reference if `Resources` has one element, and, with `resources-interop` feature,
+/// `&resources::Resources`.
+///
+/// Implementors should borrow the resources from themselves for the duration of the run,
+/// and pass them to [`Executor::run()`](struct.Executor.html#method.run) as a tuple of
+/// references (or `Option<&mut R>`, if the container doesn't have `R`). Resources behind
+/// locks can be locked for the whole run, as below, or wrapped in
+/// [`LockOnDemand`](struct.LockOnDemand.html) to only be locked while systems borrow them.
+///
+/// # Example
+/// ```rust
+/// # use yaks::{Executor, ResourceProvider};
+/// # use std::sync::{Arc, RwLock};
+/// # let world = hecs::World::new();
+/// struct Shared {
+///     gravity: Arc<RwLock<f32>>,
+///     ticks: u32,
+/// }
+///
+/// impl ResourceProvider<(f32, u32)> for &mut Shared {
+///     fn provide_and_run(self, executor: &mut Executor<(f32, u32)>, world: &hecs::World) {
+///         let mut gravity = self.gravity.write().unwrap();
+///         executor.run(world, (&mut *gravity, &mut self.ticks));
+///     }
+/// }
+///
+/// let mut executor = Executor::<(f32, u32)>::builder()
+///     .system(|_context, (gravity, ticks): (&f32, &mut u32), _queries: ()| {
+///         *ticks += *gravity as u32;
+///     })
+///     .build();
+/// let mut shared = Shared {
+///     gravity: Arc::new(RwLock::new(9.8)),
+///     ticks: 0,
+/// };
+/// executor.run(&world, &mut shared);
+/// assert_eq!(shared.ticks, 9);
+/// ```
+pub trait ResourceProvider<Resources>: Sized
+where
+    Resources: ResourceTuple,
+{
+    /// Borrows the resources and runs the executor with them.
+    fn provide_and_run(self, executor: &mut Executor<Resources>, world: &World);
+}
+
+impl ResourceProvider<()> for () {
+    fn provide_and_run(self, executor: &mut Executor<()>, world: &World) {
+        executor.run_wrapped(world, ());
+    }
+}
+
+impl<R0> ResourceProvider<(R0,)> for &mut R0
+where
+    R0: Send + Sync,
+{
+    fn provide_and_run(mut self, executor: &mut Executor<(R0,)>, world: &World) {
+        let wrapped = self.wrap(&mut executor.borrows);
+        executor.run_wrapped(world, wrapped);
+    }
+}
+
+impl<R0, S0> ResourceProvider<(R0,)> for (S0,)
+where
+    R0: Send + Sync,
+    S0: ResourceRef<Resource = R0>,
+{
+    fn provide_and_run(mut self, executor: &mut Executor<(R0,)>, world: &World) {
+        let wrapped = self.wrap(&mut executor.borrows);
+        executor.run_wrapped(world, wrapped);
+    }
+}
+
+macro_rules! impl_resource_provider {
+    ($($letter:ident),*) => {
+        paste::item! {
+            impl<$($letter,)* $([<S $letter>],)*> ResourceProvider<($($letter,)*)>
+                for ($([<S $letter>],)*)
+            where
+                $($letter: Send + Sync,)*
+                $([<S $letter>]: ResourceRef<Resource = $letter>,)*
+            {
+                fn provide_and_run(
+                    mut self,
+                    executor: &mut Executor<($($letter,)*)>,
+                    world: &World,
+                ) {
+                    let wrapped = self.wrap(&mut executor.borrows);
+                    executor.run_wrapped(world, wrapped);
+                }
+            }
+        }
+    }
+}
+
+impl_for_tuples!(impl_resource_provider);
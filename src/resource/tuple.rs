@@ -7,6 +7,25 @@ pub trait ResourceTuple {
     const LENGTH: usize;
 
     fn instantiate_borrows() -> Self::BorrowTuple;
+
+    fn wrap_owned(&mut self, borrows: &mut Self::BorrowTuple) -> Self::Wrapped;
+
+    fn set_tick(borrows: &mut Self::BorrowTuple, tick: u64);
+
+    fn change_tick(borrows: &Self::BorrowTuple, index: usize) -> u64;
+
+    /// Tick of the current run and change tick of the resource at given index.
+    fn wrapped_ticks(wrapped: &Self::Wrapped, index: usize) -> (u64, u64);
+
+    /// Whether the resource at given index lives behind a lock that excludes
+    /// shared borrows from each other, see `LockOnDemand`.
+    fn wrapped_is_exclusive(wrapped: &Self::Wrapped, index: usize) -> bool;
+
+    /// Whether the resource at given index was given as a shared reference.
+    fn wrapped_is_shared(wrapped: &Self::Wrapped, index: usize) -> bool;
+
+    /// Name of the type of the resource at given index.
+    fn type_name(index: usize) -> &'static str;
 }
 
 impl ResourceTuple for () {
@@ -15,6 +34,30 @@ impl ResourceTuple for () {
     const LENGTH: usize = 0;
 
     fn instantiate_borrows() -> Self::BorrowTuple {}
+
+    fn wrap_owned(&mut self, _: &mut Self::BorrowTuple) -> Self::Wrapped {}
+
+    fn set_tick(_: &mut Self::BorrowTuple, _: u64) {}
+
+    fn change_tick(_: &Self::BorrowTuple, _: usize) -> u64 {
+        unreachable!()
+    }
+
+    fn wrapped_ticks(_: &Self::Wrapped, _: usize) -> (u64, u64) {
+        unreachable!()
+    }
+
+    fn wrapped_is_exclusive(_: &Self::Wrapped, _: usize) -> bool {
+        unreachable!()
+    }
+
+    fn wrapped_is_shared(_: &Self::Wrapped, _: usize) -> bool {
+        unreachable!()
+    }
+
+    fn type_name(_: usize) -> &'static str {
+        unreachable!()
+    }
 }
 
 impl<R0> ResourceTuple for (R0,)
@@ -28,6 +71,34 @@ where
     fn instantiate_borrows() -> Self::BorrowTuple {
         (AtomicBorrow::new(),)
     }
+
+    fn wrap_owned(&mut self, borrows: &mut Self::BorrowTuple) -> Self::Wrapped {
+        (ResourceCell::new(&mut self.0, &mut borrows.0),)
+    }
+
+    fn set_tick(borrows: &mut Self::BorrowTuple, tick: u64) {
+        borrows.0.set_tick(tick);
+    }
+
+    fn change_tick(borrows: &Self::BorrowTuple, _: usize) -> u64 {
+        borrows.0.change_tick()
+    }
+
+    fn wrapped_ticks(wrapped: &Self::Wrapped, _: usize) -> (u64, u64) {
+        (wrapped.0.tick(), wrapped.0.change_tick())
+    }
+
+    fn wrapped_is_exclusive(wrapped: &Self::Wrapped, _: usize) -> bool {
+        wrapped.0.is_exclusive()
+    }
+
+    fn wrapped_is_shared(wrapped: &Self::Wrapped, _: usize) -> bool {
+        wrapped.0.is_shared()
+    }
+
+    fn type_name(_: usize) -> &'static str {
+        std::any::type_name::<R0>()
+    }
 }
 
 macro_rules! swap_to_atomic_borrow {
@@ -41,16 +112,59 @@ macro_rules! swap_to_atomic_borrow {
 
 macro_rules! impl_resource_tuple {
     ($($letter:ident),*) => {
-        impl<$($letter),*> ResourceTuple for ($($letter,)*)
-        where
-            $($letter: Send + Sync,)*
-        {
-            type Wrapped = ($(ResourceCell<$letter>,)*);
-            type BorrowTuple = ($(swap_to_atomic_borrow!($letter),)*);
-            const LENGTH: usize = count!($($letter)*);
-
-            fn instantiate_borrows() -> Self::BorrowTuple {
-                ($(swap_to_atomic_borrow!(new $letter),)*)
+        paste::item! {
+            impl<$($letter),*> ResourceTuple for ($($letter,)*)
+            where
+                $($letter: Send + Sync,)*
+            {
+                type Wrapped = ($(ResourceCell<$letter>,)*);
+                type BorrowTuple = ($(swap_to_atomic_borrow!($letter),)*);
+                const LENGTH: usize = count!($($letter)*);
+
+                fn instantiate_borrows() -> Self::BorrowTuple {
+                    ($(swap_to_atomic_borrow!(new $letter),)*)
+                }
+
+                #[allow(non_snake_case)]
+                fn wrap_owned(&mut self, borrows: &mut Self::BorrowTuple) -> Self::Wrapped {
+                    let ($([<S $letter>],)*) = self;
+                    let ($([<B $letter>],)*) = borrows;
+                    ($( ResourceCell::new([<S $letter>], [<B $letter>]) ,)*)
+                }
+
+                #[allow(non_snake_case)]
+                fn set_tick(borrows: &mut Self::BorrowTuple, tick: u64) {
+                    let ($([<B $letter>],)*) = borrows;
+                    $( [<B $letter>].set_tick(tick); )*
+                }
+
+                #[allow(non_snake_case)]
+                fn change_tick(borrows: &Self::BorrowTuple, index: usize) -> u64 {
+                    let ($([<B $letter>],)*) = borrows;
+                    [$( [<B $letter>] ,)*][index].change_tick()
+                }
+
+                #[allow(non_snake_case)]
+                fn wrapped_ticks(wrapped: &Self::Wrapped, index: usize) -> (u64, u64) {
+                    let ($([<W $letter>],)*) = wrapped;
+                    [$( ([<W $letter>].tick(), [<W $letter>].change_tick()) ,)*][index]
+                }
+
+                #[allow(non_snake_case)]
+                fn wrapped_is_exclusive(wrapped: &Self::Wrapped, index: usize) -> bool {
+                    let ($([<W $letter>],)*) = wrapped;
+                    [$( [<W $letter>].is_exclusive() ,)*][index]
+                }
+
+                #[allow(non_snake_case)]
+                fn wrapped_is_shared(wrapped: &Self::Wrapped, index: usize) -> bool {
+                    let ($([<W $letter>],)*) = wrapped;
+                    [$( [<W $letter>].is_shared() ,)*][index]
+                }
+
+                fn type_name(index: usize) -> &'static str {
+                    [$( std::any::type_name::<$letter>() ,)*][index]
+                }
             }
         }
     }
@@ -1,5 +1,48 @@
 use super::{AtomicBorrow, ResourceCell};
 
+/// Specifies how a reference to a resource is wrapped into a cell.
+pub trait ResourceRef {
+    type Resource: Send + Sync;
+
+    fn wrap_ref(&mut self, borrow: &mut AtomicBorrow) -> ResourceCell<Self::Resource>;
+}
+
+impl<R0> ResourceRef for &'_ mut R0
+where
+    R0: Send + Sync,
+{
+    type Resource = R0;
+
+    fn wrap_ref(&mut self, borrow: &mut AtomicBorrow) -> ResourceCell<R0> {
+        ResourceCell::new(self, borrow)
+    }
+}
+
+impl<R0> ResourceRef for &'_ R0
+where
+    R0: Send + Sync,
+{
+    type Resource = R0;
+
+    fn wrap_ref(&mut self, borrow: &mut AtomicBorrow) -> ResourceCell<R0> {
+        ResourceCell::new_shared(self, borrow)
+    }
+}
+
+impl<R0> ResourceRef for Option<&'_ mut R0>
+where
+    R0: Send + Sync,
+{
+    type Resource = R0;
+
+    fn wrap_ref(&mut self, borrow: &mut AtomicBorrow) -> ResourceCell<R0> {
+        match self {
+            Some(resource) => ResourceCell::new(resource, borrow),
+            None => ResourceCell::new_missing(borrow),
+        }
+    }
+}
+
 /// Specifies how a tuple of references is wrapped into a tuple of cells.
 pub trait ResourceWrap {
     type Wrapped: Send + Sync;
@@ -27,15 +70,15 @@ where
     }
 }
 
-impl<R0> ResourceWrap for (&'_ mut R0,)
+impl<S0> ResourceWrap for (S0,)
 where
-    R0: Send + Sync,
+    S0: ResourceRef,
 {
-    type Wrapped = (ResourceCell<R0>,);
+    type Wrapped = (ResourceCell<S0::Resource>,);
     type BorrowTuple = (AtomicBorrow,);
 
     fn wrap(&mut self, borrows: &mut Self::BorrowTuple) -> Self::Wrapped {
-        (ResourceCell::new(self.0, &mut borrows.0),)
+        (self.0.wrap_ref(&mut borrows.0),)
     }
 }
 
@@ -51,18 +94,18 @@ macro_rules! swap_to_atomic_borrow {
 macro_rules! impl_resource_wrap {
     ($($letter:ident),*) => {
         paste::item! {
-            impl<$($letter),*> ResourceWrap for ($(&'_ mut $letter,)*)
+            impl<$($letter),*> ResourceWrap for ($($letter,)*)
             where
-                $($letter: Send + Sync,)*
+                $($letter: ResourceRef,)*
             {
-                type Wrapped = ($(ResourceCell<$letter>,)*);
+                type Wrapped = ($(ResourceCell<$letter::Resource>,)*);
                 type BorrowTuple = ($(swap_to_atomic_borrow!($letter),)*);
 
                 #[allow(non_snake_case)]
                 fn wrap(&mut self, borrows: &mut Self::BorrowTuple) -> Self::Wrapped {
                     let ($([<S $letter>],)*) = self;
                     let ($([<B $letter>],)*) = borrows;
-                    ($( ResourceCell::new([<S $letter>], [<B $letter>]) ,)*)
+                    ($( [<S $letter>].wrap_ref([<B $letter>]) ,)*)
                 }
             }
         }
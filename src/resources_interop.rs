@@ -1,46 +1,132 @@
 use hecs::World;
-use resources::{Ref, RefMut, Resource, Resources};
+use resources::{CantGetResource, Ref, RefMut, Resource, Resources};
+use std::{any::type_name, error::Error, fmt};
 
-use crate::{Executor, QueryBundle, RefExtractor, System, SystemContext};
+use crate::{Executor, QueryBundle, ResourceProvider, ResourceTuple, System, SystemContext};
 
 // TODO sprinkle this in doc examples
 
-impl RefExtractor<&Resources> for () {
-    fn extract_and_run(executor: &mut Executor<Self>, world: &World, _: &Resources) {
+/// Error returned by [`Executor::try_run()`](struct.Executor.html#method.try_run) and
+/// [`System::try_run()`](trait.System.html#method.try_run) when a resource cannot be
+/// fetched from a `resources::Resources`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FetchError {
+    /// Resource of the named type is required, but is not in the container.
+    NoSuchResource(&'static str),
+    /// Resource of the named type is already borrowed in a way that conflicts
+    /// with the requested borrow.
+    InvalidBorrow(&'static str),
+}
+
+impl FetchError {
+    fn new<R0>(error: CantGetResource) -> Self {
+        match error {
+            CantGetResource::NoSuchResource(_) => FetchError::NoSuchResource(type_name::<R0>()),
+            CantGetResource::InvalidBorrow(_) => FetchError::InvalidBorrow(type_name::<R0>()),
+        }
+    }
+}
+
+impl fmt::Display for FetchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FetchError::NoSuchResource(name) => {
+                write!(f, "cannot fetch {}: no such resource", name)
+            }
+            FetchError::InvalidBorrow(name) => write!(f, "cannot fetch {}: already borrowed", name),
+        }
+    }
+}
+
+impl Error for FetchError {}
+
+/// Borrows a resource from the container mutably; a resource that is absent
+/// is only an error if a system requires it unconditionally.
+fn fetch_optional<R0>(
+    resources: &Resources,
+    required: bool,
+) -> Result<Option<RefMut<'_, R0>>, FetchError>
+where
+    R0: Resource,
+{
+    match resources.get_mut::<R0>() {
+        Ok(resource) => Ok(Some(resource)),
+        Err(CantGetResource::NoSuchResource(_)) if !required => Ok(None),
+        Err(error) => Err(FetchError::new::<R0>(error)),
+    }
+}
+
+/// Specifies how resources of an executor are fetched from a `resources::Resources`,
+/// allowing it to be used with [`Executor::try_run()`](struct.Executor.html#method.try_run),
+/// and as the resources argument of [`Executor::run()`](struct.Executor.html#method.run).
+///
+/// Implemented for `()` and tuples of up to 16 types that implement `resources::Resource`;
+/// it cannot be implemented outside of this crate.
+pub trait TryRefExtractor: ResourceTuple + Sized {
+    /// Fetches the resources and runs the executor with them, or returns an error
+    /// without running any systems if a resource is missing or already borrowed.
+    fn try_extract_and_run(
+        executor: &mut Executor<Self>,
+        world: &World,
+        resources: &Resources,
+    ) -> Result<(), FetchError>;
+}
+
+impl<T> ResourceProvider<T> for &Resources
+where
+    T: TryRefExtractor,
+{
+    fn provide_and_run(self, executor: &mut Executor<T>, world: &World) {
+        T::try_extract_and_run(executor, world, self).unwrap_or_else(|error| panic!("{}", error));
+    }
+}
+
+impl TryRefExtractor for () {
+    fn try_extract_and_run(
+        executor: &mut Executor<Self>,
+        world: &World,
+        _: &Resources,
+    ) -> Result<(), FetchError> {
         executor.run(world, ());
+        Ok(())
     }
 }
 
-impl<R0> RefExtractor<&Resources> for (R0,)
+impl<R0> TryRefExtractor for (R0,)
 where
     R0: Resource,
 {
-    fn extract_and_run(executor: &mut Executor<Self>, world: &World, resources: &Resources) {
-        let mut refs = resources
-            .fetch::<&mut R0>()
-            .unwrap_or_else(|error| panic!("{}", error));
-        let derefs = (&mut *refs,);
+    fn try_extract_and_run(
+        executor: &mut Executor<Self>,
+        world: &World,
+        resources: &Resources,
+    ) -> Result<(), FetchError> {
+        let mut refs = fetch_optional::<R0>(resources, executor.required_resources[0])?;
+        let derefs = (refs.as_deref_mut(),);
         executor.run(world, derefs);
+        Ok(())
     }
 }
 
 macro_rules! impl_ref_extractor {
     ($($letter:ident),*) => {
-        impl<'a, $($letter),*> RefExtractor<&Resources> for ($($letter,)*)
+        impl<$($letter),*> TryRefExtractor for ($($letter,)*)
         where
             $($letter: Resource,)*
         {
             #[allow(non_snake_case)]
-            fn extract_and_run(
+            fn try_extract_and_run(
                 executor: &mut Executor<Self>,
                 world: &World,
                 resources: &Resources,
-            ) {
-                let ($(mut $letter,)*) = resources
-                    .fetch::<($(&mut $letter, )*)>()
-                    .unwrap_or_else(|error| panic!("{}", error));
-                let derefs = ($(&mut *$letter,)*);
+            ) -> Result<(), FetchError> {
+                let mut required = executor.required_resources.iter().copied();
+                let ($(mut $letter,)*) = ($(
+                    fetch_optional::<$letter>(resources, required.next().unwrap_or(false))?,
+                )*);
+                let derefs = ($($letter.as_deref_mut(),)*);
                 executor.run(world, derefs);
+                Ok(())
             }
         }
     }
@@ -48,10 +134,44 @@ macro_rules! impl_ref_extractor {
 
 impl_for_tuples!(impl_ref_extractor);
 
+impl<'closures, Tuple> Executor<'closures, Tuple>
+where
+    Tuple: TryRefExtractor,
+{
+    /// Same as [`::run()`](#method.run) with a `resources::Resources` as the resources
+    /// argument, but returns an error instead of panicking if a resource is missing
+    /// or already borrowed. No systems are ran if an error is returned.
+    ///
+    /// A resource may be absent from the container if all systems that borrow it
+    /// request it as an `Option`.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use yaks::{Executor, FetchError};
+    /// # let world = hecs::World::new();
+    /// let mut executor = Executor::<(f32, u32)>::builder()
+    ///     .system(|_context, (some_f32, some_u32): (&mut f32, &u32), _queries: ()| {
+    ///         *some_f32 += *some_u32 as f32;
+    ///     })
+    ///     .build();
+    /// let mut resources = resources::Resources::new();
+    /// resources.insert(0f32);
+    /// assert_eq!(
+    ///     executor.try_run(&world, &resources),
+    ///     Err(FetchError::NoSuchResource("u32"))
+    /// );
+    /// resources.insert(1u32);
+    /// assert_eq!(executor.try_run(&world, &resources), Ok(()));
+    /// ```
+    pub fn try_run(&mut self, world: &World, resources: &Resources) -> Result<(), FetchError> {
+        Tuple::try_extract_and_run(self, world, resources)
+    }
+}
+
 pub trait Fetch<'a> {
     type Wrapped;
 
-    fn fetch(resources: &'a Resources) -> Self::Wrapped;
+    fn try_fetch(resources: &'a Resources) -> Result<Self::Wrapped, FetchError>;
 
     fn deref(wrapped: &mut Self::Wrapped) -> Self;
 }
@@ -62,8 +182,8 @@ where
 {
     type Wrapped = Ref<'a, R0>;
 
-    fn fetch(resources: &'a Resources) -> Self::Wrapped {
-        resources.get().unwrap_or_else(|error| panic!("{}", error))
+    fn try_fetch(resources: &'a Resources) -> Result<Self::Wrapped, FetchError> {
+        resources.get().map_err(FetchError::new::<R0>)
     }
 
     fn deref(wrapped: &mut Self::Wrapped) -> Self {
@@ -77,10 +197,8 @@ where
 {
     type Wrapped = RefMut<'a, R0>;
 
-    fn fetch(resources: &'a Resources) -> Self::Wrapped {
-        resources
-            .get_mut()
-            .unwrap_or_else(|error| panic!("{}", error))
+    fn try_fetch(resources: &'a Resources) -> Result<Self::Wrapped, FetchError> {
+        resources.get_mut().map_err(FetchError::new::<R0>)
     }
 
     fn deref(wrapped: &mut Self::Wrapped) -> Self {
@@ -88,6 +206,48 @@ where
     }
 }
 
+impl<'a, R0> Fetch<'a> for Option<&'_ R0>
+where
+    R0: Resource,
+{
+    type Wrapped = Option<Ref<'a, R0>>;
+
+    fn try_fetch(resources: &'a Resources) -> Result<Self::Wrapped, FetchError> {
+        match resources.get() {
+            Ok(resource) => Ok(Some(resource)),
+            Err(CantGetResource::NoSuchResource(_)) => Ok(None),
+            Err(error) => Err(FetchError::new::<R0>(error)),
+        }
+    }
+
+    fn deref(wrapped: &mut Self::Wrapped) -> Self {
+        wrapped
+            .as_ref()
+            .map(|wrapped| unsafe { std::mem::transmute(&**wrapped) })
+    }
+}
+
+impl<'a, R0> Fetch<'a> for Option<&'_ mut R0>
+where
+    R0: Resource,
+{
+    type Wrapped = Option<RefMut<'a, R0>>;
+
+    fn try_fetch(resources: &'a Resources) -> Result<Self::Wrapped, FetchError> {
+        match resources.get_mut() {
+            Ok(resource) => Ok(Some(resource)),
+            Err(CantGetResource::NoSuchResource(_)) => Ok(None),
+            Err(error) => Err(FetchError::new::<R0>(error)),
+        }
+    }
+
+    fn deref(wrapped: &mut Self::Wrapped) -> Self {
+        wrapped
+            .as_mut()
+            .map(|wrapped| unsafe { std::mem::transmute(&mut **wrapped) })
+    }
+}
+
 impl<'a, 'closure, Closure, Queries> System<'closure, (), Queries, &'a Resources, Resources>
     for Closure
 where
@@ -98,6 +258,11 @@ where
     fn run(&mut self, world: &World, _: &'a Resources) {
         self.run(world, ());
     }
+
+    fn try_run(&mut self, world: &World, _: &'a Resources) -> Result<(), FetchError> {
+        self.run(world, ());
+        Ok(())
+    }
 }
 
 impl<'a, 'closure, Closure, A, Queries> System<'closure, A, Queries, &'a Resources, Resources>
@@ -109,8 +274,16 @@ where
     Queries: QueryBundle,
 {
     fn run(&mut self, world: &World, resources: &'a Resources) {
-        let mut refs = A::fetch(resources);
+        <Self as System<'closure, A, Queries, &'a Resources, Resources>>::try_run(
+            self, world, resources,
+        )
+        .unwrap_or_else(|error| panic!("{}", error));
+    }
+
+    fn try_run(&mut self, world: &World, resources: &'a Resources) -> Result<(), FetchError> {
+        let mut refs = A::try_fetch(resources)?;
         self.run(world, A::deref(&mut refs));
+        Ok(())
     }
 }
 
@@ -124,10 +297,26 @@ macro_rules! impl_system {
             $(for<'r> $letter: Fetch<'r>,)*
             Queries: QueryBundle,
         {
-            #[allow(non_snake_case)]
             fn run(&mut self, world: &World, resources: &'a Resources) {
-                let ($(mut $letter,)*) = ($($letter::fetch(resources),)*);
+                <Self as System<
+                    'closure,
+                    ($($letter),*),
+                    Queries,
+                    &'a Resources,
+                    Resources,
+                >>::try_run(self, world, resources)
+                .unwrap_or_else(|error| panic!("{}", error));
+            }
+
+            #[allow(non_snake_case)]
+            fn try_run(
+                &mut self,
+                world: &World,
+                resources: &'a Resources,
+            ) -> Result<(), FetchError> {
+                let ($(mut $letter,)*) = ($($letter::try_fetch(resources)?,)*);
                 self.run(world, ($($letter::deref(&mut $letter),)*));
+                Ok(())
             }
         }
     }
@@ -165,4 +354,52 @@ fn smoke_test() {
     assert_eq!(*resources.get::<i32>().unwrap(), 4);
     sum_system.run(&world, &resources);
     assert_eq!(*resources.get::<i32>().unwrap(), 7);
+
+    fn optional_system(_: SystemContext, (a, b): (&mut i32, Option<&i8>), _: ()) {
+        *a += b.map_or(-1, |b| *b as i32);
+    }
+    optional_system.run(&world, &resources);
+    assert_eq!(*resources.get::<i32>().unwrap(), 6);
+    resources.insert(2i8);
+    optional_system.run(&world, &resources);
+    assert_eq!(*resources.get::<i32>().unwrap(), 8);
+
+    let mut executor = Executor::<(i32, i16)>::builder()
+        .system(|_, (a, b): (&mut i32, Option<&mut i16>), _: ()| {
+            assert!(b.is_none());
+            *a = 0;
+        })
+        .build();
+    executor.run(&world, &resources);
+    assert_eq!(*resources.get::<i32>().unwrap(), 0);
+
+    let mut executor = Executor::<(i32, u16)>::builder()
+        .system(|_, (a, b): (&mut i32, &u16), _: ()| {
+            *a += *b as i32;
+        })
+        .build();
+    assert_eq!(
+        executor.try_run(&world, &resources),
+        Err(FetchError::NoSuchResource("u16"))
+    );
+    assert_eq!(sum_system.try_run(&world, &resources), Ok(()),);
+    resources.remove::<usize>();
+    assert_eq!(
+        sum_system.try_run(&world, &resources),
+        Err(FetchError::NoSuchResource("usize"))
+    );
+    resources.insert(1u16);
+    {
+        let _borrow = resources.get::<i32>().unwrap();
+        assert_eq!(
+            executor.try_run(&world, &resources),
+            Err(FetchError::InvalidBorrow("i32"))
+        );
+        assert_eq!(
+            optional_system.try_run(&world, &resources),
+            Err(FetchError::InvalidBorrow("i32"))
+        );
+    }
+    assert_eq!(executor.try_run(&world, &resources), Ok(()));
+    assert_eq!(*resources.get::<i32>().unwrap(), 4);
 }
@@ -2,12 +2,24 @@ use hecs::World;
 
 use crate::{QueryBundle, SystemContext};
 
+#[cfg(feature = "resources-interop")]
+use crate::FetchError;
+
 // TODO improve doc
 /// Automatically implemented on all closures and functions than
 /// can be used as systems in an executor.
 pub trait System<'closure, Resources, Queries, RefSource, Marker> {
     /// Zero-cost wrapping function that executes the system.
     fn run(&mut self, world: &World, resources: RefSource);
+
+    /// Same as [`::run()`](#tymethod.run), but returns an error instead of panicking
+    /// if resources cannot be fetched from a `resources::Resources`.
+    /// Always succeeds with other kinds of resources argument.
+    #[cfg(feature = "resources-interop")]
+    fn try_run(&mut self, world: &World, resources: RefSource) -> Result<(), FetchError> {
+        self.run(world, resources);
+        Ok(())
+    }
 }
 
 impl<'closure, Closure, Resources, Queries> System<'closure, Resources, Queries, Resources, ()>
@@ -22,6 +34,9 @@ where
             SystemContext {
                 system_id: None,
                 world,
+                tick: 0,
+                handle: None,
+                delta: None,
             },
             resources,
             Queries::markers(),
@@ -1,8 +1,12 @@
 use hecs::{
     Archetype, ArchetypesGeneration, Entity, NoSuchEntity, Query, QueryBorrow, QueryOne, World,
 };
+use std::time::Duration;
 
-use crate::{QueryMarker, SystemId};
+use crate::{
+    query_entities::{EntityBorrows, QueryEntities, QueryMany, QueryManyError},
+    QueryMarker, SystemId,
+};
 
 /// Thin wrapper over [`hecs::World`](../hecs/struct.World.html), can prepare queries using a
 /// [`QueryMarker`](struct.QueryMarker.html).
@@ -12,6 +16,9 @@ use crate::{QueryMarker, SystemId};
 pub struct SystemContext<'scope> {
     pub(crate) system_id: Option<SystemId>,
     pub(crate) world: &'scope World,
+    pub(crate) tick: u64,
+    pub(crate) handle: Option<&'scope str>,
+    pub(crate) delta: Option<Duration>,
 }
 
 impl<'scope> SystemContext<'scope> {
@@ -22,6 +29,53 @@ impl<'scope> SystemContext<'scope> {
         self.system_id
     }
 
+    /// Returns the tick of the current run of the executor the system is in,
+    /// or 0 if it's called as a plain function; see
+    /// [`Executor::tick()`](struct.Executor.html#method.tick).
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// Returns the `Debug` representation of the handle the system was inserted into
+    /// the [`ExecutorBuilder`](struct.ExecutorBuilder.html) with, if any; see
+    /// [`ExecutorBuilder::system_with_handle()`][swh].
+    ///
+    /// [swh]: struct.ExecutorBuilder.html#method.system_with_handle
+    pub fn handle_debug(&self) -> Option<&str> {
+        self.handle
+    }
+
+    /// Returns the wall-clock time elapsed since the system last started running
+    /// in it's executor, or `None` if it's running for the first time or is called
+    /// as a plain function.
+    ///
+    /// Runs in which a reactive system was skipped are not counted; see
+    /// [`ExecutorBuilder::reactive()`](struct.ExecutorBuilder.html#method.reactive).
+    ///
+    /// # Example
+    /// ```rust
+    /// # use yaks::Executor;
+    /// # let world = hecs::World::new();
+    /// struct Position(f32);
+    ///
+    /// let mut position = Position(0.0);
+    /// let mut executor = Executor::<(Position,)>::builder()
+    ///     .system_with_handle(
+    ///         |context, position: &mut Position, _queries: ()| {
+    ///             assert_eq!(context.handle_debug(), Some("\"movement\""));
+    ///             let delta = context.delta().unwrap_or_default();
+    ///             position.0 += 5.0 * delta.as_secs_f32();
+    ///         },
+    ///         "movement",
+    ///     )
+    ///     .build();
+    /// executor.run(&world, &mut position);
+    /// executor.run(&world, &mut position);
+    /// ```
+    pub fn delta(&self) -> Option<Duration> {
+        self.delta
+    }
+
     /// Prepares a query using the given [`QueryMarker`](struct.QueryMarker.html);
     /// see [`hecs::World::query()`](../hecs/struct.World.html#method.query).
     ///
@@ -103,6 +157,99 @@ impl<'scope> SystemContext<'scope> {
         self.world.query_one(entity)
     }
 
+    /// Prepares a query against a list of entities using the given
+    /// [`QueryMarker`](struct.QueryMarker.html), to be processed in parallel by
+    /// [`yaks::batch_entities()`](fn.batch_entities.html).
+    ///
+    /// Borrows the queried components in archetypes of the entities right away,
+    /// panicking if that conflicts with existing borrows, same as
+    /// [`hecs::QueryOne::get()`](../hecs/struct.QueryOne.html#method.get) would.
+    ///
+    /// Panics if an entity is listed more than once.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use yaks::{SystemContext, QueryMarker};
+    /// # #[derive(Default)]
+    /// # struct Pos;
+    /// # struct Vel;
+    /// # let world = hecs::World::new();
+    /// fn some_system(
+    ///     context: SystemContext,
+    ///     selection: &Vec<hecs::Entity>,
+    ///     query: QueryMarker<(&mut Pos, &Vel)>
+    /// ) {
+    ///     yaks::batch_entities(
+    ///         &mut context.query_entities(query, selection),
+    ///         |_entity, item| {
+    ///             if let Some((pos, _vel)) = item {
+    ///                 *pos = Pos::default();
+    ///             }
+    ///         },
+    ///     );
+    /// };
+    /// ```
+    pub fn query_entities<Q>(&self, _: QueryMarker<Q>, entities: &[Entity]) -> QueryEntities<'_, Q>
+    where
+        Q: Query + Send + Sync,
+    {
+        match EntityBorrows::new(self.world, entities) {
+            Ok(borrows) => QueryEntities { borrows },
+            Err(duplicate) => panic!("{}", QueryManyError::Duplicate(duplicate)),
+        }
+    }
+
+    /// Prepares a query against several distinct entities using the given
+    /// [`QueryMarker`](struct.QueryMarker.html); unlike holding several
+    /// [`hecs::QueryOne`](../hecs/struct.QueryOne.html)s, their items can be borrowed
+    /// mutably at the same time, even if the entities share an archetype.
+    ///
+    /// Returns an error if an entity is listed more than once, does not exist, or
+    /// does not match the query. Borrows the queried components in archetypes of
+    /// the entities right away, panicking if that conflicts with existing borrows.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use yaks::{SystemContext, QueryMarker};
+    /// # struct Transform(f32);
+    /// # struct Parent(hecs::Entity);
+    /// # let world = hecs::World::new();
+    /// fn some_system(
+    ///     context: SystemContext,
+    ///     _resources: (),
+    ///     (children, transforms): (QueryMarker<&Parent>, QueryMarker<&mut Transform>)
+    /// ) {
+    ///     let links: Vec<_> = context
+    ///         .query(children)
+    ///         .iter()
+    ///         .map(|(child, parent)| (parent.0, child))
+    ///         .collect();
+    ///     for (parent, child) in links {
+    ///         let mut query_many = context
+    ///             .query_many(transforms, [parent, child])
+    ///             .expect("invalid parent");
+    ///         let [parent, child] = query_many.get();
+    ///         child.0 += parent.0;
+    ///     }
+    /// };
+    /// ```
+    pub fn query_many<Q, const N: usize>(
+        &self,
+        _: QueryMarker<Q>,
+        entities: [Entity; N],
+    ) -> Result<QueryMany<'_, Q, N>, QueryManyError>
+    where
+        Q: Query + Send + Sync,
+    {
+        let borrows =
+            EntityBorrows::new(self.world, &entities).map_err(QueryManyError::Duplicate)?;
+        match borrows.first_missing() {
+            Some(entity) if self.world.contains(entity) => Err(QueryManyError::Unsatisfied(entity)),
+            Some(entity) => Err(QueryManyError::NoSuchEntity(entity)),
+            None => Ok(QueryMany { borrows }),
+        }
+    }
+
     /// See [`hecs::World::reserve_entity()`](../hecs/struct.World.html#method.reserve_entity).
     pub fn reserve_entity(&self) -> Entity {
         self.world.reserve_entity()
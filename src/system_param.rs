@@ -0,0 +1,206 @@
+use hecs::World;
+
+use crate::QueryAccess;
+#[cfg(feature = "parallel")]
+use crate::{ArchetypeSet, BorrowFieldSet, BorrowSet, BorrowTypeSet, ComponentAccess};
+
+/// Registers a finalizer with the resource cells behind the pointer.
+pub(crate) type Finalizer = fn(*const ());
+
+/// Collects resources and components a system parameter may borrow;
+/// see [`SystemParam`](trait.SystemParam.html).
+///
+/// It cannot be instantiated directly, an executor creates one for each system
+/// while building.
+pub struct SystemAccess {
+    #[cfg(feature = "parallel")]
+    pub(crate) resource_set: BorrowSet,
+    #[cfg(feature = "parallel")]
+    pub(crate) resource_field_set: BorrowFieldSet,
+    #[cfg(feature = "parallel")]
+    pub(crate) component_type_set: BorrowTypeSet,
+    #[cfg(feature = "parallel")]
+    pub(crate) archetype_writers: Vec<fn(&World, &mut ArchetypeSet)>,
+    #[cfg(feature = "resources-interop")]
+    pub(crate) required_resources: Vec<usize>,
+    /// Resources the system reads; changes to them cause reactive systems to run.
+    pub(crate) inputs: Vec<usize>,
+    /// Resources the system may modify; these can't be given to the executor
+    /// as shared references.
+    pub(crate) outputs: Vec<usize>,
+    /// Resources finalized at the end of every run of the executor, whether the system
+    /// runs or not, and functions registering their finalizers with the executor's cells.
+    pub(crate) finalizers: Vec<(usize, Finalizer)>,
+}
+
+impl SystemAccess {
+    #[cfg_attr(not(feature = "parallel"), allow(unused_variables))]
+    pub(crate) fn new(resources: usize) -> Self {
+        Self {
+            #[cfg(feature = "parallel")]
+            resource_set: BorrowSet::with_capacity(resources),
+            #[cfg(feature = "parallel")]
+            resource_field_set: BorrowFieldSet::new(),
+            #[cfg(feature = "parallel")]
+            component_type_set: BorrowTypeSet::new(),
+            #[cfg(feature = "parallel")]
+            archetype_writers: Vec::new(),
+            #[cfg(feature = "resources-interop")]
+            required_resources: Vec::new(),
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            finalizers: Vec::new(),
+        }
+    }
+
+    /// Declares that the system may execute queries of type `Q0`.
+    pub fn query<Q0>(&mut self)
+    where
+        Q0: QueryAccess,
+    {
+        #[cfg(feature = "parallel")]
+        {
+            Q0::declare_access(&mut ComponentAccess::new(&mut self.component_type_set));
+            self.archetype_writers
+                .push(|world, archetype_set| archetype_set.set_bits_for_query::<Q0>(world));
+        }
+    }
+}
+
+/// Specifies how a value passed as the resources argument of a system is obtained
+/// each time the system is ran in an executor.
+///
+/// Implemented for references and mutable references to resources, and for tuples
+/// (up to 16) of system parameters. `Cells` is the executor's internal resource storage
+/// and `Marker` disambiguates implementations; both should be kept generic and passed
+/// through to any parameters the implementor is built from.
+///
+/// Each system owns a `State` for every parameter in it's signature, created when the system
+/// is inserted into an [`ExecutorBuilder`](struct.ExecutorBuilder.html) and kept for
+/// the lifetime of the executor.
+///
+/// # Example
+/// ```rust
+/// # use yaks::{Executor, SystemAccess, SystemParam};
+/// # let world = hecs::World::new();
+/// struct Gravity(f32);
+///
+/// /// Counts how many times the system has ran.
+/// struct Runs<'a>(&'a mut u32);
+///
+/// impl<'a, Cells> SystemParam<'a, Cells, ()> for Runs<'a> {
+///     type State = u32;
+///
+///     fn init_state() -> Self::State {
+///         0
+///     }
+///
+///     fn declare_access(_: &mut SystemAccess) {}
+///
+///     fn fetch(state: &'a mut Self::State, _: &'a Cells, _: &'a hecs::World) -> Self {
+///         *state += 1;
+///         Runs(state)
+///     }
+///
+///     unsafe fn release(_: &'a Cells) {}
+/// }
+///
+/// let mut gravity = Gravity(9.8);
+/// let mut executor = Executor::<(Gravity,)>::builder()
+///     .system(|_context, (gravity, runs): (&Gravity, Runs), _queries: ()| {
+///         assert!(*runs.0 < 3);
+///     })
+///     .build();
+/// executor.run(&world, &mut gravity);
+/// executor.run(&world, &mut gravity);
+/// ```
+pub trait SystemParam<'a, Cells, Marker>: Sized {
+    /// Data owned by the system, persisting between it's runs.
+    type State: Send + Sync + 'static;
+
+    /// Creates the state; called once per system, when it's inserted into a builder.
+    fn init_state() -> Self::State;
+
+    /// Declares resources and components the parameter may borrow. Implementors built from
+    /// other parameters must call this function of each of them.
+    fn declare_access(access: &mut SystemAccess);
+
+    /// Creates the parameter right before the system runs.
+    fn fetch(state: &'a mut Self::State, resources: &'a Cells, world: &'a World) -> Self;
+
+    /// Releases any borrows made in [`::fetch()`](#tymethod.fetch), after the system returns.
+    ///
+    /// # Safety
+    /// Must be called exactly once for every call to `::fetch()`, after the fetched value
+    /// is no longer used.
+    unsafe fn release(resources: &'a Cells);
+}
+
+impl<'a, Cells> SystemParam<'a, Cells, ()> for () {
+    type State = ();
+
+    fn init_state() -> Self::State {}
+
+    fn declare_access(_: &mut SystemAccess) {}
+
+    fn fetch(_: &'a mut Self::State, _: &'a Cells, _: &'a World) -> Self {}
+
+    unsafe fn release(_: &'a Cells) {}
+}
+
+impl<'a, Cells, M0, P0> SystemParam<'a, Cells, (M0,)> for (P0,)
+where
+    P0: SystemParam<'a, Cells, M0>,
+{
+    type State = (P0::State,);
+
+    fn init_state() -> Self::State {
+        (P0::init_state(),)
+    }
+
+    fn declare_access(access: &mut SystemAccess) {
+        P0::declare_access(access);
+    }
+
+    fn fetch(state: &'a mut Self::State, resources: &'a Cells, world: &'a World) -> Self {
+        (P0::fetch(&mut state.0, resources, world),)
+    }
+
+    unsafe fn release(resources: &'a Cells) {
+        P0::release(resources);
+    }
+}
+
+macro_rules! impl_system_param {
+    ($($letter:ident),*) => {
+        paste::item! {
+            impl<'a, Cells, $([<M $letter>],)* $([<P $letter>],)*>
+                SystemParam<'a, Cells, ($([<M $letter>],)*)> for ($([<P $letter>]),*)
+            where
+                $([<P $letter>]: SystemParam<'a, Cells, [<M $letter>]>,)*
+            {
+                type State = ($([<P $letter>]::State),*);
+
+                fn init_state() -> Self::State {
+                    ($([<P $letter>]::init_state()),*)
+                }
+
+                fn declare_access(access: &mut SystemAccess) {
+                    $([<P $letter>]::declare_access(access);)*
+                }
+
+                #[allow(non_snake_case)]
+                fn fetch(state: &'a mut Self::State, resources: &'a Cells, world: &'a World) -> Self {
+                    let ($([<S $letter>]),*) = state;
+                    ($([<P $letter>]::fetch([<S $letter>], resources, world)),*)
+                }
+
+                unsafe fn release(resources: &'a Cells) {
+                    $([<P $letter>]::release(resources);)*
+                }
+            }
+        }
+    }
+}
+
+impl_for_tuples!(impl_system_param);
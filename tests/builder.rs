@@ -30,9 +30,7 @@ fn invalid_dependency() {
 }
 
 #[test]
-#[should_panic(
-    expected = "could not resolve dependencies of a handle-less system: no system 1 found"
-)]
+#[should_panic(expected = "dummy_system: no system 1 found")]
 fn invalid_dependency_no_handle() {
     Executor::<()>::builder()
         .system_with_handle(dummy_system, 0)
@@ -48,3 +46,11 @@ fn self_dependency() {
         .system_with_handle_and_deps(dummy_system, 1, vec![1])
         .build();
 }
+
+#[test]
+#[should_panic(expected = "cannot make system 1 reactive: no such system found")]
+fn reactive_invalid_handle() {
+    let _ = Executor::<()>::builder()
+        .system_with_handle(dummy_system, 0)
+        .reactive(1);
+}
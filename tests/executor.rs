@@ -1,5 +1,18 @@
 use hecs::World;
-use yaks::{Executor, QueryMarker};
+use parking_lot::{Mutex, RwLock};
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use yaks::{
+    Accumulate, Accumulator, Back, BatchSize, Changed, ComponentAccess, DoubleBuffered,
+    EventReader, EventWriter, Events, Executor, Field, FieldMut, Front, Labeled, Local,
+    LockOnDemand, QueryAccess, QueryManyError, QueryMarker, ResourceProvider, SystemAccess,
+    SystemContext, SystemParam,
+};
 
 struct A(usize);
 
@@ -40,6 +53,33 @@ fn systems_two() {
     assert_eq!(a.0, 3);
 }
 
+#[test]
+#[cfg(feature = "parallel")]
+fn systems_thread_pools() {
+    let world = World::new();
+    let mut a = A(0);
+    let mut b = B(1);
+    let mut c = C(2);
+    let runs = AtomicUsize::new(0);
+    let mut executor = Executor::<(A, B, C)>::builder()
+        .system_with_handle(|_, (a, b): (&mut A, &B), _: ()| a.0 += b.0, 0)
+        .system_with_deps(|_, (a, c): (&mut A, &C), _: ()| a.0 += c.0, vec![0])
+        .system(|_, _: (&B, &C), _: ()| {
+            runs.fetch_add(1, Ordering::Relaxed);
+        })
+        .build();
+    for threads in 1..=3 {
+        let thread_pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap();
+        thread_pool.install(|| executor.run(&world, (&mut a, &mut b, &mut c)));
+    }
+    drop(executor);
+    assert_eq!(a.0, 9);
+    assert_eq!(runs.into_inner(), 3);
+}
+
 #[test]
 fn resources_decoding_single() {
     let world = World::new();
@@ -176,3 +216,1169 @@ fn invalid_resources_mutable_mutable() {
         .build();
     executor.run(&world, (&mut a, &mut b, &mut c));
 }
+
+struct Fields {
+    a: A,
+    b: B,
+}
+
+yaks::resource_fields!(Fields { a: A, b: B });
+
+#[test]
+fn resources_fields() {
+    fn add_b_twice(
+        _: SystemContext,
+        (mut a, b, also_b): (FieldMut<Fields, A>, Field<Fields, B>, Field<Fields, B>),
+        _: (),
+    ) {
+        a.0 += b.0 + also_b.0;
+    }
+    let world = World::new();
+    let mut fields = Fields { a: A(0), b: B(1) };
+    let mut executor = Executor::<(Fields,)>::builder()
+        .system(add_b_twice)
+        .system(|_, fields: &Fields, _: ()| assert_eq!(fields.b.0, 1))
+        .build();
+    executor.run(&world, &mut fields);
+    executor.run(&world, &mut fields);
+    assert_eq!(fields.a.0, 4);
+}
+
+#[test]
+#[should_panic(expected = "cannot borrow a field of executor::Fields mutably: already borrowed")]
+fn invalid_resources_field_mutable_mutable() {
+    let world = World::new();
+    let mut fields = Fields { a: A(0), b: B(1) };
+    let mut executor = Executor::<(Fields,)>::builder()
+        .system(|_, _: (FieldMut<Fields, A>, FieldMut<Fields, A>), _: ()| {})
+        .build();
+    executor.run(&world, &mut fields);
+}
+
+#[test]
+#[should_panic(
+    expected = "cannot borrow executor::Fields immutably: a field of it is already borrowed mutably"
+)]
+fn invalid_resources_field_mutable_whole() {
+    let world = World::new();
+    let mut fields = Fields { a: A(0), b: B(1) };
+    let mut executor = Executor::<(Fields,)>::builder()
+        .system(|_, _: (FieldMut<Fields, A>, &Fields), _: ()| {})
+        .build();
+    executor.run(&world, &mut fields);
+}
+
+#[test]
+#[should_panic(expected = "cannot borrow a field of executor::Fields mutably: \
+    the whole of it is already borrowed immutably")]
+fn invalid_resources_whole_field_mutable() {
+    let world = World::new();
+    let mut fields = Fields { a: A(0), b: B(1) };
+    let mut executor = Executor::<(Fields,)>::builder()
+        .system(|_, _: (&Fields, FieldMut<Fields, B>), _: ()| {})
+        .build();
+    executor.run(&world, &mut fields);
+}
+
+#[test]
+#[should_panic(expected = "already borrowed (in system \"conflicting\")")]
+fn invalid_resources_system_name() {
+    let world = World::new();
+    let mut a = A(0);
+    let mut b = B(1);
+    let mut c = C(2);
+    let mut executor = Executor::<(A, B, C)>::builder()
+        .system_with_handle(|_, _: (&B, &mut B), _: ()| {}, "conflicting")
+        .build();
+    executor.run(&world, (&mut a, &mut b, &mut c));
+}
+
+struct CustomQuery;
+
+impl hecs::Query for CustomQuery {
+    type Fetch = <(&'static mut A, &'static B) as hecs::Query>::Fetch;
+}
+
+impl QueryAccess for CustomQuery {
+    fn declare_access(access: &mut ComponentAccess) {
+        access.write::<A>();
+        access.read::<B>();
+    }
+}
+
+#[test]
+fn queries_custom() {
+    let mut world = World::new();
+    world.spawn((A(0), B(1)));
+    world.spawn((A(0), B(2)));
+    world.spawn((A(0),));
+    let mut executor = Executor::<()>::builder()
+        .system(|context, _: (), query: QueryMarker<CustomQuery>| {
+            for (_, (a, b)) in context.query(query).iter() {
+                a.0 += b.0;
+            }
+        })
+        .system(|context, _: (), query: QueryMarker<&A>| {
+            for (_, a) in context.query(query).iter() {
+                assert!(a.0 < 3);
+            }
+        })
+        .build();
+    executor.run(&world, ());
+    let mut sum = 0;
+    for (_, a) in world.query::<&A>().iter() {
+        sum += a.0;
+    }
+    assert_eq!(sum, 3);
+}
+
+struct Counted<'a>(&'a mut A, usize);
+
+impl<'a, Cells, M0> SystemParam<'a, Cells, M0> for Counted<'a>
+where
+    &'a mut A: SystemParam<'a, Cells, M0>,
+{
+    type State = (<&'a mut A as SystemParam<'a, Cells, M0>>::State, usize);
+
+    fn init_state() -> Self::State {
+        (<&'a mut A as SystemParam<'a, Cells, M0>>::init_state(), 0)
+    }
+
+    fn declare_access(access: &mut SystemAccess) {
+        <&'a mut A as SystemParam<'a, Cells, M0>>::declare_access(access);
+    }
+
+    fn fetch(state: &'a mut Self::State, resources: &'a Cells, world: &'a World) -> Self {
+        let (inner, count) = state;
+        *count += 1;
+        Counted(
+            <&'a mut A as SystemParam<'a, Cells, M0>>::fetch(inner, resources, world),
+            *count,
+        )
+    }
+
+    unsafe fn release(resources: &'a Cells) {
+        <&'a mut A as SystemParam<'a, Cells, M0>>::release(resources);
+    }
+}
+
+#[test]
+fn custom_system_param() {
+    let world = World::new();
+    let mut a = A(0);
+    let mut b = B(1);
+    let mut executor = Executor::<(A, B)>::builder()
+        .system(|_, (counted, b): (Counted, &B), _: ()| {
+            counted.0 .0 += counted.1 * b.0;
+        })
+        .system(|_, a: &mut A, _: ()| {
+            a.0 += 10;
+        })
+        .build();
+    executor.run(&world, (&mut a, &mut b));
+    executor.run(&world, (&mut a, &mut b));
+    assert_eq!(a.0, 23);
+}
+
+#[test]
+fn local_state() {
+    let world = World::new();
+    let mut a = A(0);
+    fn accumulate(_: SystemContext, (a, mut runs): (&mut A, Local<usize>), _: ()) {
+        *runs += 1;
+        a.0 += *runs;
+    }
+    let mut executor = Executor::<(A,)>::builder()
+        .system(accumulate)
+        .system(accumulate)
+        .build();
+    executor.run(&world, &mut a);
+    assert_eq!(a.0, 2);
+    executor.run(&world, &mut a);
+    assert_eq!(a.0, 6);
+}
+
+#[test]
+fn resources_shared() {
+    let world = World::new();
+    let mut a = A(0);
+    let b = B(1);
+    let c = C(2);
+    let mut executor = Executor::<(A, B, C)>::builder()
+        .system(|_, (a, b): (&mut A, &B), _: ()| {
+            a.0 += b.0;
+        })
+        .system(|_, (a, c): (&mut A, &C), _: ()| {
+            a.0 += c.0;
+        })
+        .build();
+    executor.run(&world, (&mut a, &b, &c));
+    assert_eq!(a.0, 3);
+}
+
+#[test]
+fn resources_custom_provider() {
+    struct Container {
+        a: A,
+        b: Option<B>,
+    }
+
+    impl ResourceProvider<(A, B)> for &mut Container {
+        fn provide_and_run(self, executor: &mut Executor<(A, B)>, world: &World) {
+            executor.run(world, (&mut self.a, self.b.as_mut()));
+        }
+    }
+
+    let world = World::new();
+    let mut container = Container {
+        a: A(0),
+        b: Some(B(1)),
+    };
+    let mut executor = Executor::<(A, B)>::builder()
+        .system(|_, (a, b): (&mut A, Option<&B>), _: ()| {
+            a.0 += b.map_or(10, |b| b.0);
+        })
+        .build();
+    executor.run(&world, &mut container);
+    assert_eq!(container.a.0, 1);
+    container.b = None;
+    executor.run(&world, &mut container);
+    assert_eq!(container.a.0, 11);
+}
+
+#[test]
+fn resources_lock_on_demand() {
+    let world = World::new();
+    let a = Arc::new(RwLock::new(A(0)));
+    let b = Arc::new(Mutex::new(B(1)));
+    let mut c = C(2);
+    let (a_clone, b_clone) = (a.clone(), b.clone());
+    let mut executor = Executor::<(A, B, C)>::builder()
+        .system(move |_, c: &mut C, _: ()| {
+            assert!(a_clone.try_write().is_some());
+            assert!(b_clone.try_lock().is_some());
+            c.0 += 1;
+        })
+        // Conflicts with the other system over `C`, so they never run concurrently.
+        .system(|_, (a, b, _): (&mut A, &B, &C), _: ()| {
+            a.0 += b.0;
+        })
+        .build();
+    executor.run(
+        &world,
+        (LockOnDemand::new(&*a), LockOnDemand::new(&*b), &mut c),
+    );
+    executor.run(
+        &world,
+        (LockOnDemand::new(&*a), LockOnDemand::new(&*b), &mut c),
+    );
+    assert_eq!(a.read().0, 2);
+    assert!(b.try_lock().is_some());
+    assert_eq!(c.0, 4);
+}
+
+#[test]
+fn resources_lock_on_demand_std() {
+    let world = World::new();
+    let a = std::sync::RwLock::new(A(0));
+    let b = std::sync::Mutex::new(B(1));
+    let mut executor = Executor::<(A, B)>::builder()
+        .system(|_, (a, b): (&mut A, &B), _: ()| {
+            a.0 += b.0;
+        })
+        .build();
+    executor.run(&world, (LockOnDemand::new(&a), LockOnDemand::new(&b)));
+    executor.run(&world, (LockOnDemand::new(&a), LockOnDemand::new(&b)));
+    assert_eq!(a.read().unwrap().0, 2);
+    assert!(b.try_lock().is_ok());
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn resources_lock_on_demand_exclusive() {
+    let world = World::new();
+    let b = Mutex::new(B(1));
+    let mut c = C(0);
+    let inside = AtomicUsize::new(0);
+    let read = |_: SystemContext, _: &B, _: ()| {
+        assert_eq!(inside.fetch_add(1, Ordering::SeqCst), 0);
+        std::thread::sleep(Duration::from_millis(10));
+        inside.fetch_sub(1, Ordering::SeqCst);
+    };
+    // Disjoint systems without dependencies are dispatched.
+    let mut dispatched = Executor::<(B, C)>::builder()
+        .system(read)
+        .system(read)
+        .build();
+    // Dependencies require scheduling.
+    let mut scheduled = Executor::<(B, C)>::builder()
+        .system_with_handle(read, 0)
+        .system(read)
+        .system_with_deps(|_, c: &mut C, _: ()| c.0 += 1, vec![0])
+        .build();
+    // The scheduler occupies a thread of its own while systems run.
+    let thread_pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(3)
+        .build()
+        .unwrap();
+    thread_pool.install(|| {
+        dispatched.run(&world, (LockOnDemand::new(&b), &mut c));
+        scheduled.run(&world, (LockOnDemand::new(&b), &mut c));
+    });
+    assert_eq!(c.0, 1);
+    assert!(b.try_lock().is_some());
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn resources_lock_on_demand_shared() {
+    let world = World::new();
+    let b = RwLock::new(B(1));
+    let mut c = C(0);
+    let inside = AtomicUsize::new(0);
+    let together = AtomicUsize::new(0);
+    let read = |_: SystemContext, _: &B, _: ()| {
+        inside.fetch_add(1, Ordering::SeqCst);
+        let start = std::time::Instant::now();
+        while inside.load(Ordering::SeqCst) < 2 && start.elapsed() < Duration::from_secs(5) {
+            std::thread::yield_now();
+        }
+        if inside.load(Ordering::SeqCst) == 2 {
+            together.fetch_add(1, Ordering::SeqCst);
+        }
+        std::thread::sleep(Duration::from_millis(10));
+        inside.fetch_sub(1, Ordering::SeqCst);
+    };
+    // Disjoint systems without dependencies are dispatched.
+    let mut dispatched = Executor::<(B, C)>::builder()
+        .system(read)
+        .system(read)
+        .build();
+    // Dependencies require scheduling.
+    let mut scheduled = Executor::<(B, C)>::builder()
+        .system_with_handle(read, 0)
+        .system(read)
+        .system_with_deps(|_, c: &mut C, _: ()| c.0 += 1, vec![0])
+        .build();
+    // The scheduler occupies a thread of its own while systems run.
+    let thread_pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(3)
+        .build()
+        .unwrap();
+    thread_pool.install(|| {
+        dispatched.run(&world, (LockOnDemand::new(&b), &mut c));
+        assert_eq!(together.swap(0, Ordering::SeqCst), 2);
+        scheduled.run(&world, (LockOnDemand::new(&b), &mut c));
+        assert_eq!(together.swap(0, Ordering::SeqCst), 2);
+    });
+    assert_eq!(c.0, 1);
+    assert!(b.try_write().is_some());
+}
+
+#[test]
+fn resources_lock_on_demand_panic() {
+    let world = World::new();
+    let a = RwLock::new(A(0));
+    let b = std::sync::Mutex::new(B(0));
+    let mut executor = Executor::<(A, B)>::builder()
+        .system(|_, (a, b): (&mut A, &mut B), _: ()| {
+            a.0 += 1;
+            b.0 += 1;
+            panic!("system panicked");
+        })
+        .build();
+    let mut run = || {
+        std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            executor.run(&world, (LockOnDemand::new(&a), LockOnDemand::new(&b)))
+        }))
+        .unwrap_err()
+    };
+    assert_eq!(run().downcast_ref(), Some(&"system panicked"));
+    assert_eq!(a.try_write().unwrap().0, 1);
+    assert!(b.is_poisoned());
+    let message = run().downcast::<String>().unwrap();
+    assert!(message.contains("lock is poisoned"), "{}", message);
+    assert!(a.try_write().is_some());
+}
+
+#[test]
+fn resources_labeled() {
+    let world = World::new();
+    let mut a0 = A(0);
+    let mut a1 = A(1);
+    let mut executor = Executor::<(Labeled<0, A>, Labeled<1, A>)>::builder()
+        .system(|_, (a0, a1): (&mut Labeled<0, A>, &Labeled<1, A>), _: ()| {
+            a0.0 += a1.0;
+        })
+        .system(|_, a1: &mut Labeled<1, A>, _: ()| {
+            a1.0 += 1;
+        })
+        .build();
+    executor.run(
+        &world,
+        (Labeled::from_mut(&mut a0), Labeled::from_mut(&mut a1)),
+    );
+    assert!(a0.0 == 1 || a0.0 == 2);
+    assert_eq!(a1.0, 2);
+}
+
+#[test]
+fn resources_accumulated() {
+    #[derive(Default)]
+    struct Stats(Vec<usize>);
+
+    impl Accumulate for Stats {
+        fn combine(&mut self, partial: Self) {
+            self.0.extend(partial.0);
+        }
+    }
+
+    let mut world = World::new();
+    world.spawn_batch((0..100).map(|index| (A(index),)));
+    let mut stats = Stats(vec![]);
+    let mut executor = Executor::<(Stats,)>::builder()
+        .system(
+            |context, stats: Accumulator<Stats>, query: QueryMarker<&A>| {
+                yaks::batch(&mut context.query(query), 8, |_, a| {
+                    stats.with(|stats| stats.0.push(a.0));
+                });
+            },
+        )
+        .system(|_, stats: Accumulator<Stats>, _: ()| {
+            stats.with(|stats| stats.0.push(100));
+        })
+        .system(|_, stats: &mut Stats, _: ()| {
+            stats.0.push(101);
+        })
+        .build();
+    executor.run(&world, &mut stats);
+    stats.0.sort_unstable();
+    assert_eq!(stats.0, (0..102).collect::<Vec<_>>());
+}
+
+#[test]
+fn resources_accumulated_nested() {
+    #[derive(Default)]
+    struct Sum(usize);
+
+    impl Accumulate for Sum {
+        fn combine(&mut self, partial: Self) {
+            self.0 += partial.0;
+        }
+    }
+
+    let mut world = World::new();
+    world.spawn_batch((0..100).map(|index| (A(index),)));
+    let mut sum = Sum(0);
+    let mut executor = Executor::<(Sum,)>::builder()
+        .system(|context, sum: Accumulator<Sum>, query: QueryMarker<&A>| {
+            sum.with(|outer| {
+                yaks::batch(&mut context.query(query), 8, |_, a| {
+                    sum.with(|sum| sum.0 += a.0);
+                });
+                outer.0 += 100;
+            });
+        })
+        .build();
+    executor.run(&world, &mut sum);
+    assert_eq!(sum.0, 4950 + 100);
+}
+
+#[test]
+fn resources_double_buffered() {
+    let world = World::new();
+    let mut buffers = DoubleBuffered::from_buffers(A(1), A(1));
+    let mut executor = Executor::<(DoubleBuffered<A>,)>::builder()
+        .system(|_, (front, mut back): (Front<A>, Back<A>), _: ()| {
+            back.0 = front.0 * 2;
+        })
+        .build();
+    executor.run(&world, &mut buffers);
+    assert_eq!(buffers.front().0, 2);
+    assert_eq!(buffers.back().0, 1);
+    executor.run(&world, &mut buffers);
+    assert_eq!(buffers.front().0, 4);
+    assert_eq!(buffers.back().0, 2);
+}
+
+#[test]
+fn resources_double_buffered_skipped() {
+    let world = World::new();
+    let mut buffers = DoubleBuffered::from_buffers(A(0), A(0));
+    let mut executor = Executor::<(DoubleBuffered<A>,)>::builder()
+        .system_with_handle(|_, mut back: Back<A>, _: ()| back.0 += 1, 0)
+        .reactive(0)
+        .build();
+    executor.run(&world, &mut buffers);
+    assert_eq!((buffers.front().0, buffers.back().0), (1, 0));
+    executor.run(&world, &mut buffers);
+    assert_eq!((buffers.front().0, buffers.back().0), (0, 1));
+}
+
+#[test]
+#[should_panic(expected = "mutably: already borrowed")]
+fn invalid_resources_double_buffered_back_back() {
+    let world = World::new();
+    let mut buffers = DoubleBuffered::from_buffers(A(1), A(1));
+    let mut executor = Executor::<(DoubleBuffered<A>,)>::builder()
+        .system(|_, _: (Back<A>, Back<A>), _: ()| {})
+        .build();
+    executor.run(&world, &mut buffers);
+}
+
+#[test]
+#[should_panic(expected = "immutably: a field of it is already borrowed mutably")]
+fn invalid_resources_double_buffered_back_whole() {
+    let world = World::new();
+    let mut buffers = DoubleBuffered::from_buffers(A(1), A(1));
+    let mut executor = Executor::<(DoubleBuffered<A>,)>::builder()
+        .system(|_, _: (Back<A>, &DoubleBuffered<A>), _: ()| {})
+        .build();
+    executor.run(&world, &mut buffers);
+}
+
+#[test]
+fn resources_double_buffered_front_whole() {
+    let world = World::new();
+    let mut buffers = DoubleBuffered::from_buffers(A(1), A(2));
+    let mut executor = Executor::<(DoubleBuffered<A>,)>::builder()
+        .system(|_, (front, whole): (Front<A>, &DoubleBuffered<A>), _: ()| {
+            assert_eq!(front.0, whole.front().0);
+        })
+        .build();
+    executor.run(&world, &mut buffers);
+}
+
+#[test]
+#[should_panic(expected = "mutably: already borrowed")]
+fn invalid_resources_double_buffered_front_whole_mut() {
+    let world = World::new();
+    let mut buffers = DoubleBuffered::from_buffers(A(1), A(1));
+    let mut executor = Executor::<(DoubleBuffered<A>,)>::builder()
+        .system(|_, _: (Front<A>, &mut DoubleBuffered<A>), _: ()| {})
+        .build();
+    executor.run(&world, &mut buffers);
+}
+
+#[test]
+#[should_panic(expected = "was given as a shared reference, but system")]
+fn invalid_resources_double_buffered_shared() {
+    let world = World::new();
+    let buffers = DoubleBuffered::from_buffers(A(1), A(1));
+    let mut executor = Executor::<(DoubleBuffered<A>,)>::builder()
+        .system(|_, front: Front<A>, _: ()| {
+            let _ = front.0;
+        })
+        .build();
+    executor.run(&world, (&buffers,));
+}
+
+#[test]
+fn resources_events() {
+    let world = World::new();
+    let mut events = Events::new();
+    let mut seen = Vec::new();
+    let mut sent = 0;
+    let mut executor = Executor::<(Events<usize>, Vec<usize>)>::builder()
+        .system_with_handle(
+            |_, mut writer: EventWriter<usize>, _: ()| {
+                sent += 1;
+                writer.send(sent);
+            },
+            0,
+        )
+        .system_with_deps(
+            |_, (mut reader, seen): (EventReader<usize>, &mut Vec<usize>), _: ()| {
+                seen.extend(reader.iter());
+            },
+            vec![0],
+        )
+        .build();
+    executor.run(&world, (&mut events, &mut seen));
+    assert_eq!(seen, vec![1]);
+    executor.run(&world, (&mut events, &mut seen));
+    assert_eq!(seen, vec![1, 2]);
+    // Events sent in the first run were dropped at the end of the second.
+    let mut cursor = 0;
+    assert_eq!(
+        EventReader::new(&events, &mut cursor)
+            .iter()
+            .copied()
+            .collect::<Vec<_>>(),
+        vec![2]
+    );
+}
+
+#[test]
+fn resources_change_ticks() {
+    let world = World::new();
+    let mut a = A(0);
+    let mut b = B(0);
+    let mut executor = Executor::<(A, B)>::builder()
+        .system(|_, a: &mut A, _: ()| {
+            a.0 += 1;
+        })
+        .system(|_, b: Option<&mut B>, _: ()| {
+            if let Some(b) = b {
+                b.0 += 1;
+            }
+        })
+        .build();
+    assert_eq!(executor.tick(), 0);
+    assert_eq!(executor.change_tick::<A, _>(), 0);
+    executor.run(&world, (&mut a, Some(&mut b)));
+    assert_eq!(executor.tick(), 1);
+    assert_eq!(executor.change_tick::<A, _>(), 1);
+    assert_eq!(executor.change_tick::<B, _>(), 1);
+    executor.run(&world, (&mut a, None));
+    assert_eq!(executor.tick(), 2);
+    assert!(executor.changed_since::<A, _>(1));
+    assert!(!executor.changed_since::<B, _>(1));
+}
+
+#[test]
+fn resources_changed() {
+    let world = World::new();
+    let mut a = A(0);
+    let mut b = B(0);
+    let mut c = C(0);
+    let mut executor = Executor::<(A, B, C)>::builder()
+        .system_with_handle(
+            |_, (c, changed): (&mut C, Changed<B>), _: ()| {
+                if changed.is_changed() {
+                    c.0 += 1;
+                }
+            },
+            0,
+        )
+        .system_with_handle_and_deps(
+            |_, b: Option<&mut B>, _: ()| {
+                if let Some(b) = b {
+                    b.0 += 1;
+                }
+            },
+            1,
+            vec![0],
+        )
+        .system_with_deps(
+            |_, (a, changed): (&mut A, Changed<B>), _: ()| {
+                if changed.is_changed() {
+                    a.0 += 1;
+                }
+            },
+            vec![1],
+        )
+        .build();
+    executor.run(&world, (&mut a, None, &mut c));
+    assert_eq!((a.0, c.0), (0, 0));
+    executor.run(&world, (&mut a, Some(&mut b), &mut c));
+    assert_eq!((a.0, c.0), (1, 0));
+    // Each change is reported once, in the first run of each system after it.
+    executor.run(&world, (&mut a, None, &mut c));
+    assert_eq!((a.0, c.0), (1, 1));
+    executor.run(&world, (&mut a, None, &mut c));
+    assert_eq!((a.0, c.0), (1, 1));
+    assert_eq!(executor.change_tick::<A, _>(), 4);
+    assert_eq!(executor.change_tick::<B, _>(), 2);
+}
+
+#[test]
+fn systems_run_metadata() {
+    use yaks::System;
+    fn plain(context: SystemContext, _: (), _: ()) {
+        assert_eq!(context.tick(), 0);
+        assert_eq!(context.handle_debug(), None);
+        assert_eq!(context.delta(), None);
+    }
+    let world = World::new();
+    let runs = Mutex::new(Vec::new());
+    let mut executor = Executor::<()>::builder()
+        .system(|context, _resources: (), _queries: ()| {
+            runs.lock().push((
+                context.tick(),
+                context.handle_debug().map(String::from),
+                context.delta(),
+            ));
+        })
+        .system_with_handle(
+            |context, _resources: (), _queries: ()| {
+                std::thread::sleep(Duration::from_millis(1));
+                runs.lock().push((
+                    context.tick(),
+                    context.handle_debug().map(String::from),
+                    context.delta(),
+                ));
+            },
+            "second",
+        )
+        .build();
+    executor.run(&world, ());
+    executor.run(&world, ());
+    executor.run(&world, ());
+    drop(executor);
+    let mut runs = runs.into_inner();
+    runs.sort_by_key(|(tick, handle, _)| (*tick, handle.clone()));
+    let metadata: Vec<_> = runs
+        .iter()
+        .map(|(tick, handle, delta)| (*tick, handle.as_deref(), delta.is_some()))
+        .collect();
+    assert_eq!(
+        metadata,
+        vec![
+            (1, None, false),
+            (1, Some("\"second\""), false),
+            (2, None, true),
+            (2, Some("\"second\""), true),
+            (3, None, true),
+            (3, Some("\"second\""), true),
+        ]
+    );
+    assert!(runs[3].2.unwrap() >= Duration::from_millis(1));
+    plain.run(&world, ());
+}
+
+#[test]
+fn systems_names() {
+    fn named(_: SystemContext, _: (), _: ()) {}
+    let executor = Executor::<()>::builder()
+        .system(named)
+        .system_with_handle(named, "second")
+        .system(|_, _: (), _: ()| {})
+        .build();
+    let names: Vec<_> = executor.system_names().collect();
+    assert_eq!(names.len(), 3);
+    assert!(names[0].ends_with("::named"));
+    assert_eq!(names[1], "\"second\"");
+    assert!(names[2].contains("systems_names"));
+}
+
+#[test]
+fn systems_reactive() {
+    let mut world = World::new();
+    let mut a = A(0);
+    let mut b = B(0);
+    let mut c = C(0);
+    let mut executor = Executor::<(A, B, C)>::builder()
+        .system_with_handle(
+            |_, b: Option<&mut B>, _: ()| {
+                if let Some(b) = b {
+                    b.0 += 1;
+                }
+            },
+            0,
+        )
+        .system_with_handle_and_deps(
+            |_, (a, _): (&mut A, Option<&B>), _: ()| a.0 += 1,
+            1,
+            vec![0],
+        )
+        .reactive(1)
+        .system_with_deps(|_, c: &mut C, _: ()| c.0 += 1, vec![1])
+        .build();
+    executor.run(&world, (&mut a, Some(&mut b), &mut c));
+    assert_eq!(a.0, 1);
+    // The change was made before the system ran, and so was already observed.
+    executor.run(&world, (&mut a, None, &mut c));
+    assert_eq!(a.0, 1);
+    world.spawn((A(0),));
+    executor.run(&world, (&mut a, None, &mut c));
+    assert_eq!(a.0, 2);
+    executor.run(&world, (&mut a, None, &mut c));
+    assert_eq!(a.0, 2);
+    executor.run(&world, (&mut a, Some(&mut b), &mut c));
+    assert_eq!(a.0, 3);
+    assert_eq!(c.0, 5);
+}
+
+#[test]
+fn systems_reactive_dirty_check() {
+    let world = World::new();
+    let mut a = A(0);
+    let mut b = B(0);
+    let checks = AtomicUsize::new(0);
+    let mut executor = Executor::<(A, B)>::builder()
+        .system_with_handle(|_, a: &mut A, _: ()| a.0 += 1, 0)
+        .system_with_handle(|_, b: &mut B, _: ()| b.0 += 1, 1)
+        .reactive_with(0, |_| false)
+        .reactive_with(1, |_| {
+            checks.fetch_add(1, Ordering::Relaxed);
+            true
+        })
+        .build();
+    for _ in 0..3 {
+        executor.run(&world, (&mut a, &mut b));
+    }
+    drop(executor);
+    assert_eq!(a.0, 1);
+    assert_eq!(b.0, 3);
+    // Called on the first run too, even though the system runs regardless.
+    assert_eq!(checks.into_inner(), 3);
+}
+
+#[test]
+fn queries_par_iter() {
+    #[cfg(feature = "parallel")]
+    use rayon::iter::ParallelIterator;
+
+    let mut world = World::new();
+    world.spawn_batch((0..100).map(|index| (A(index), B(0))));
+    let mut c = C(0);
+    let mut executor = Executor::<(C,)>::builder()
+        .system(|context, c: &mut C, query: QueryMarker<(&A, &mut B)>| {
+            c.0 = yaks::par_iter(&mut context.query(query), 8)
+                .filter(|(_, (a, _))| a.0 % 2 == 0)
+                .map(|(_, (a, b))| {
+                    b.0 = a.0;
+                    a.0
+                })
+                .sum();
+        })
+        .build();
+    executor.run(&world, &mut c);
+    assert_eq!(c.0, (0..100).filter(|index| index % 2 == 0).sum());
+    for (_, (a, b)) in world.query::<(&A, &B)>().iter() {
+        assert_eq!(b.0, if a.0 % 2 == 0 { a.0 } else { 0 });
+    }
+}
+
+#[test]
+fn queries_batch_size() {
+    let mut world = World::new();
+    world.spawn_batch((0..1000).map(|index| (A(index), B(0))));
+    world.spawn_batch((0..10).map(|index| (A(index), B(0), C(0))));
+    world.spawn((A(0), B(0), 0u8));
+    let mut executor = Executor::<()>::builder()
+        .system(
+            |context, _resources: (), query: QueryMarker<(&A, &mut B)>| {
+                yaks::batch(
+                    &mut context.query(query),
+                    BatchSize::auto(context.archetypes()),
+                    |_, (a, b)| b.0 += a.0 + 1,
+                );
+                yaks::batch(
+                    &mut context.query(query),
+                    BatchSize::auto(context.archetypes()).with_item_cost(Duration::from_micros(1)),
+                    |_, (_, b)| b.0 += 1,
+                );
+                yaks::batch(&mut context.query(query), 0, |_, (_, b)| b.0 += 1);
+            },
+        )
+        .build();
+    executor.run(&world, ());
+    for (_, (a, b)) in world.query::<(&A, &B)>().iter() {
+        assert_eq!(b.0, a.0 + 3);
+    }
+}
+
+#[test]
+fn queries_batch_thread_pools() {
+    let mut world = World::new();
+    world.spawn_batch((0..1000).map(|index| (A(index), B(0))));
+    world.spawn_batch((0..300).map(|index| (A(index), B(0), C(0))));
+    world.spawn_batch((0..3).map(|index| (A(index), B(0), 0u8)));
+    let increment = |world: &World| {
+        yaks::batch(&mut world.query::<&mut B>(), 7, |_, b| b.0 += 1);
+        yaks::batch(
+            &mut world.query::<&mut B>(),
+            BatchSize::auto(world.archetypes()),
+            |_, b| b.0 += 1,
+        );
+    };
+    increment(&world);
+    #[cfg(feature = "parallel")]
+    for threads in 1..=4 {
+        let thread_pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap();
+        thread_pool.install(|| increment(&world));
+    }
+    #[cfg(feature = "parallel")]
+    let expected = 10;
+    #[cfg(not(feature = "parallel"))]
+    let expected = 2;
+    for (_, b) in world.query::<&B>().iter() {
+        assert_eq!(b.0, expected);
+    }
+}
+
+#[test]
+fn queries_batch_pairs() {
+    let mut world = World::new();
+    world.spawn_batch((0..40).map(|index| (A(index),)));
+    world.spawn_batch((40..57).map(|index| (A(index), B(0))));
+    let pairs = |world: &World, tile_size| {
+        let mut pairs: Vec<_> = yaks::batch_pairs_with(
+            &mut world.query::<&A>(),
+            tile_size,
+            Vec::new,
+            |pairs, _, first, _, second| pairs.push((first.0, second.0)),
+        )
+        .into_iter()
+        .flatten()
+        .map(|(first, second)| (first.min(second), first.max(second)))
+        .collect();
+        pairs.sort_unstable();
+        pairs
+    };
+    let expected: Vec<_> = (0..57)
+        .flat_map(|first| ((first + 1)..57).map(move |second| (first, second)))
+        .collect();
+    for tile_size in [0, 1, 5, 16, 57, 100] {
+        assert_eq!(pairs(&world, tile_size), expected);
+        #[cfg(feature = "parallel")]
+        for threads in 2..=4 {
+            let thread_pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap();
+            assert_eq!(thread_pool.install(|| pairs(&world, tile_size)), expected);
+        }
+    }
+    let count = Mutex::new(0);
+    yaks::batch_pairs(&mut world.query::<&B>(), 4, |_, _, _, _| *count.lock() += 1);
+    assert_eq!(*count.lock(), 17 * 16 / 2);
+}
+
+#[test]
+fn queries_entities() {
+    let mut world = World::new();
+    let with_b: Vec<_> = world
+        .spawn_batch((0..50).map(|index| (A(index), B(0))))
+        .collect();
+    let without_b = world.spawn((A(0),));
+    let despawned = world.spawn((A(0), B(0)));
+    world.despawn(despawned).unwrap();
+    let mut entities: Vec<_> = with_b.iter().copied().step_by(2).collect();
+    entities.push(without_b);
+    entities.push(despawned);
+    let mut c = C(0);
+    let mut executor = Executor::<(Vec<hecs::Entity>, C)>::builder()
+        .system(
+            |context,
+             (entities, c): (&Vec<hecs::Entity>, &mut C),
+             query: QueryMarker<(&A, &mut B)>| {
+                let missing = AtomicUsize::new(0);
+                let mut query_entities = context.query_entities(query, entities);
+                assert_eq!(query_entities.entities(), &entities[..]);
+                yaks::batch_entities(&mut query_entities, |_, item| match item {
+                    Some((a, b)) => b.0 = a.0 + 1,
+                    None => {
+                        missing.fetch_add(1, Ordering::Relaxed);
+                    }
+                });
+                c.0 = missing.into_inner();
+            },
+        )
+        .build();
+    executor.run(&world, (&mut entities, &mut c));
+    assert_eq!(c.0, 2);
+    for (index, entity) in with_b.iter().enumerate() {
+        let expected = if index % 2 == 0 { index + 1 } else { 0 };
+        assert_eq!(world.get::<B>(*entity).unwrap().0, expected);
+    }
+}
+
+#[test]
+#[should_panic(expected = "is listed more than once")]
+fn invalid_queries_entities_duplicate() {
+    let mut world = World::new();
+    let entity = world.spawn((A(0), B(0)));
+    let mut entities = vec![entity, entity];
+    let mut executor = Executor::<(Vec<hecs::Entity>,)>::builder()
+        .system(
+            |context, entities: &Vec<hecs::Entity>, query: QueryMarker<&mut B>| {
+                yaks::batch_entities(&mut context.query_entities(query, entities), |_, _| {});
+            },
+        )
+        .build();
+    executor.run(&world, &mut entities);
+}
+
+#[test]
+fn queries_many() {
+    let mut world = World::new();
+    let first = world.spawn((A(1), B(0)));
+    let second = world.spawn((A(2), B(0)));
+    let other = world.spawn((A(3), B(0), C(0)));
+    let unsatisfied = world.spawn((A(4),));
+    let despawned = world.spawn((A(5), B(0)));
+    world.despawn(despawned).unwrap();
+    let mut executor = Executor::<()>::builder()
+        .system(move |context, _resources: (), query: QueryMarker<&mut B>| {
+            {
+                let mut query_many = context.query_many(query, [first, second, other]).unwrap();
+                let [first, second, other] = query_many.get();
+                first.0 = 1;
+                second.0 = first.0 + 1;
+                other.0 = second.0 + 1;
+            }
+            assert_eq!(
+                context.query_many(query, [first, first]).err(),
+                Some(QueryManyError::Duplicate(first))
+            );
+            assert_eq!(
+                context.query_many(query, [first, unsatisfied]).err(),
+                Some(QueryManyError::Unsatisfied(unsatisfied))
+            );
+            assert_eq!(
+                context.query_many(query, [despawned, first]).err(),
+                Some(QueryManyError::NoSuchEntity(despawned))
+            );
+            let mut query_many = context.query_many(query, [second]).unwrap();
+            let [second] = query_many.get();
+            second.0 *= 10;
+        })
+        .build();
+    executor.run(&world, ());
+    assert_eq!(world.get::<B>(first).unwrap().0, 1);
+    assert_eq!(world.get::<B>(second).unwrap().0, 20);
+    assert_eq!(world.get::<B>(other).unwrap().0, 3);
+}
+
+#[test]
+fn queries_batch_reduce_deterministic() {
+    struct F(f32);
+
+    let mut world = World::new();
+    world.spawn_batch((0..1000).map(|index| (F(1.0 / (index as f32 + 1.0)),)));
+    world.spawn_batch((0..1000).map(|index| (F(index as f32 * 1000.0), A(index))));
+    let reduce = |world: &World| {
+        yaks::batch_reduce(
+            &mut world.query::<&F>(),
+            7,
+            || 0.0f32,
+            |sum, _, f| sum + f.0,
+            |left, right| left + right,
+        )
+    };
+    let expected = reduce(&world).to_bits();
+    assert_eq!(reduce(&world).to_bits(), expected);
+    #[cfg(feature = "parallel")]
+    for threads in 1..=4 {
+        let thread_pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap();
+        for _ in 0..10 {
+            assert_eq!(thread_pool.install(|| reduce(&world)).to_bits(), expected);
+        }
+    }
+    let empty = yaks::batch_reduce(
+        &mut world.query::<&B>(),
+        7,
+        || 1usize,
+        |count, _, _| count + 1,
+        |left, right| left + right,
+    );
+    assert_eq!(empty, 1);
+}
+
+#[test]
+fn resources_optional() {
+    let world = World::new();
+    let mut a = A(0);
+    let mut b = B(1);
+    let mut executor = Executor::<(A, B)>::builder()
+        .system(|_, (a, b): (&mut A, Option<&B>), _: ()| {
+            a.0 += b.map_or(10, |b| b.0);
+        })
+        .system(|_, (a, b): (&A, Option<&mut B>), _: ()| {
+            if let Some(b) = b {
+                b.0 += a.0;
+            }
+        })
+        .build();
+    executor.run(&world, (&mut a, Some(&mut b)));
+    assert_eq!(a.0, 1);
+    executor.run(&world, (&mut a, None));
+    assert_eq!(a.0, 11);
+}
+
+#[test]
+#[should_panic(expected = "cannot borrow executor::B immutably: no such resource")]
+fn invalid_resources_missing() {
+    let world = World::new();
+    let mut a = A(0);
+    let mut executor = Executor::<(A, B)>::builder()
+        .system(|_, _: (&A, &B), _: ()| {})
+        .build();
+    executor.run(&world, (&mut a, None));
+}
+
+#[test]
+#[should_panic(
+    expected = "cannot run the executor: executor::B was given as a shared reference, \
+    but system \"mutable\" may modify it"
+)]
+fn invalid_resources_shared_mutable() {
+    let world = World::new();
+    let mut a = A(0);
+    let b = B(1);
+    let mut executor = Executor::<(A, B)>::builder()
+        .system_with_handle(|_, _: (&A, &mut B), _: ()| {}, "mutable")
+        .build();
+    executor.run(&world, (&mut a, &b));
+}
+
+#[test]
+#[should_panic(expected = "cannot run the executor")]
+fn invalid_resources_shared_accumulator() {
+    #[derive(Default)]
+    struct Sum(usize);
+
+    impl Accumulate for Sum {
+        fn combine(&mut self, partial: Self) {
+            self.0 += partial.0;
+        }
+    }
+
+    let world = World::new();
+    let sum = Sum(0);
+    let mut executor = Executor::<(Sum,)>::builder()
+        // Never accumulates anything, but still may.
+        .system(|_, _: Accumulator<Sum>, _: ()| {})
+        .build();
+    executor.run(&world, (&sum,));
+}
+
+#[test]
+fn resources_shared_single() {
+    let world = World::new();
+    let a = A(1);
+    let sum = AtomicUsize::new(0);
+    let mut executor = Executor::<(A,)>::builder()
+        .system(|_, a: &A, _: ()| {
+            sum.fetch_add(a.0, Ordering::Relaxed);
+        })
+        .build();
+    executor.run(&world, (&a,));
+    drop(executor);
+    assert_eq!(sum.into_inner(), 1);
+}
+
+#[test]
+fn resources_owned() {
+    let world = World::new();
+    let mut executor = Executor::<(A, B, C)>::builder()
+        .system(|_, (a, b): (&mut A, &B), _: ()| {
+            a.0 += b.0;
+        })
+        .system(|_, (a, c): (&mut A, &C), _: ()| {
+            a.0 += c.0;
+        })
+        .build_with_resources((A(0), B(1), C(2)));
+    executor.run_owned(&world);
+    assert_eq!(executor.resource::<A, _>().0, 3);
+    executor.resource_mut::<C, _>().0 = 10;
+    executor.run_owned(&world);
+    assert_eq!(executor.resource::<A, _>().0, 14);
+}
+
+#[test]
+#[should_panic(expected = "executor does not own its resources")]
+fn resources_not_owned() {
+    let world = World::new();
+    let mut executor = Executor::<(A,)>::builder().build();
+    executor.run_owned(&world);
+}